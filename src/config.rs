@@ -0,0 +1,657 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diagnostics::Severity,
+    error::{Result, WeztermocilError},
+    layout::{
+        Diagram, Layout, LayoutOptions, LayoutOptionsError, LayoutRegistry, ParseLayoutError,
+    },
+    wezterm::pane::{PaneSize, ParseSizeError, Size},
+};
+
+mod check;
+mod tree;
+
+pub use check::validate_file;
+pub use tree::{SplitNodeConfig, SplitTreeConfig};
+
+/// A single pane in a window's `panes` list.
+///
+/// Panes can either be a bare command or a hash of options, and the two can
+/// be mixed within the same window.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PaneConfig {
+    Command(String),
+    Options(PaneConfigOptions),
+}
+
+impl PaneConfig {
+    pub fn commands(&self) -> Vec<String> {
+        match self {
+            PaneConfig::Command(command) => vec![command.clone()],
+            PaneConfig::Options(options) => options.commands.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn focus(&self) -> bool {
+        match self {
+            // If it's just a command, you can't focus the pane
+            PaneConfig::Command(_) => false,
+            PaneConfig::Options(options) => options.focus,
+        }
+    }
+
+    pub fn root(&self) -> Option<&str> {
+        match self {
+            PaneConfig::Command(_) => None,
+            PaneConfig::Options(options) => options.root.as_deref(),
+        }
+    }
+}
+
+impl From<&str> for PaneConfig {
+    fn from(command: &str) -> Self {
+        PaneConfig::Command(String::from(command))
+    }
+}
+
+impl From<PaneConfigOptions> for PaneConfig {
+    fn from(options: PaneConfigOptions) -> Self {
+        PaneConfig::Options(options)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PaneConfigOptions {
+    pub commands: Option<Vec<String>>,
+    #[serde(default)]
+    pub focus: bool,
+    pub root: Option<String>,
+}
+
+impl PaneConfigOptions {
+    pub fn new() -> PaneConfigOptions {
+        PaneConfigOptions::default()
+    }
+
+    /// Add a command to run when the pane is created.
+    pub fn command(mut self, command: &str) -> PaneConfigOptions {
+        self.commands
+            .get_or_insert_with(Vec::new)
+            .push(String::from(command));
+        self
+    }
+
+    /// Select this pane once the session has launched.
+    pub fn focus(mut self) -> PaneConfigOptions {
+        self.focus = true;
+        self
+    }
+
+    /// Set the directory the pane starts in, relative to the window's `root`.
+    pub fn root(mut self, root: &str) -> PaneConfigOptions {
+        self.root = Some(String::from(root));
+        self
+    }
+}
+
+/// A size as written in a layout file. Bare numbers are percentages, and
+/// strings can be either `65%` or `80c` for a number of cells.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SizeConfig {
+    Percent(i64),
+    Size(String),
+}
+
+impl SizeConfig {
+    pub fn parse(&self) -> std::result::Result<Size, ParseSizeError> {
+        match self {
+            SizeConfig::Percent(percent) => percent.to_string().parse(),
+            SizeConfig::Size(size) => size.parse(),
+        }
+    }
+}
+
+impl From<&str> for SizeConfig {
+    fn from(size: &str) -> Self {
+        SizeConfig::Size(String::from(size))
+    }
+}
+
+/// A window's `layout`: either a layout name, or breakpoints picking one by
+/// how big the window's first pane is.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum LayoutConfig {
+    Name(String),
+    Breakpoints(Vec<BreakpointConfig>),
+}
+
+/// A layout used when the window's first pane has at least `min_cols`
+/// columns and `min_rows` rows.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BreakpointConfig {
+    pub min_cols: Option<usize>,
+    pub min_rows: Option<usize>,
+    pub layout: String,
+}
+
+impl BreakpointConfig {
+    pub fn new(layout: &str) -> BreakpointConfig {
+        BreakpointConfig {
+            min_cols: None,
+            min_rows: None,
+            layout: String::from(layout),
+        }
+    }
+
+    pub fn min_cols(mut self, columns: usize) -> BreakpointConfig {
+        self.min_cols = Some(columns);
+        self
+    }
+
+    pub fn min_rows(mut self, rows: usize) -> BreakpointConfig {
+        self.min_rows = Some(rows);
+        self
+    }
+
+    /// Whether the breakpoint has no minimums, so it's always picked.
+    pub fn matches_any_size(&self) -> bool {
+        self.min_cols.is_none() && self.min_rows.is_none()
+    }
+
+    pub fn fits(&self, size: PaneSize) -> bool {
+        self.min_cols.is_none_or(|columns| size.columns >= columns)
+            && self.min_rows.is_none_or(|rows| size.rows >= rows)
+    }
+}
+
+/// Returned when a `root` uses an environment variable that isn't set.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RootError {
+    pub root: String,
+    pub variable: String,
+}
+
+impl fmt::Display for RootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`${}` isn't set in the environment", self.variable)
+    }
+}
+
+impl Error for RootError {}
+
+/// The directory the layout file at `path` is in, as an absolute path.
+pub(crate) fn layout_dir(path: &str) -> std::io::Result<PathBuf> {
+    let dir = match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    std::path::absolute(dir)
+}
+
+/// Expand `~` and environment variables in `root`, and resolve it against
+/// `base_dir` when it's relative.
+fn resolve_root(root: &str, base_dir: Option<&Path>) -> std::result::Result<String, RootError> {
+    let expanded = shellexpand::full(root).map_err(|error| RootError {
+        root: String::from(root),
+        variable: error.var_name,
+    })?;
+
+    Ok(match base_dir {
+        // Collecting the components drops the `.` in `./services/api`.
+        Some(base_dir) => base_dir
+            .join(expanded.as_ref())
+            .components()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .into_owned(),
+        None => expanded.into_owned(),
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct WindowConfig {
+    pub name: Option<String>,
+    pub root: Option<String>,
+    pub layout: Option<LayoutConfig>,
+    pub panes: Option<Vec<PaneConfig>>,
+    pub command: Option<String>,
+    pub commands: Option<Vec<String>>,
+    #[serde(default)]
+    pub focus: bool,
+    pub main_size: Option<SizeConfig>,
+    pub column_sizes: Option<Vec<SizeConfig>>,
+    pub tree: Option<SplitTreeConfig>,
+    pub diagram: Option<String>,
+    pub columns: Option<usize>,
+    pub rows: Option<usize>,
+    #[serde(default)]
+    pub create_root: bool,
+}
+
+impl WindowConfig {
+    pub fn new() -> WindowConfig {
+        WindowConfig::default()
+    }
+
+    /// Set the tab title.
+    pub fn name(mut self, name: &str) -> WindowConfig {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Set the directory the window's panes start in.
+    pub fn root(mut self, root: &str) -> WindowConfig {
+        self.root = Some(String::from(root));
+        self
+    }
+
+    /// Create the window's `root`, and the `root` of each of its panes, when
+    /// they don't exist yet.
+    pub fn create_root(mut self) -> WindowConfig {
+        self.create_root = true;
+        self
+    }
+
+    /// Set the layout by name, e.g. `main-vertical`.
+    pub fn layout(mut self, layout: &str) -> WindowConfig {
+        self.layout = Some(LayoutConfig::Name(String::from(layout)));
+        self
+    }
+
+    /// Add a layout picked by the size of the window's first pane, after
+    /// any breakpoints added before it. Replaces a layout set by name.
+    pub fn breakpoint(mut self, breakpoint: BreakpointConfig) -> WindowConfig {
+        match &mut self.layout {
+            Some(LayoutConfig::Breakpoints(breakpoints)) => breakpoints.push(breakpoint),
+            _ => self.layout = Some(LayoutConfig::Breakpoints(vec![breakpoint])),
+        }
+        self
+    }
+
+    /// Add a pane, either a bare command or [`PaneConfigOptions`].
+    pub fn pane(mut self, pane: impl Into<PaneConfig>) -> WindowConfig {
+        self.panes.get_or_insert_with(Vec::new).push(pane.into());
+        self
+    }
+
+    /// Add a command run in every pane of the window before the pane's own commands.
+    pub fn command(mut self, command: &str) -> WindowConfig {
+        self.commands
+            .get_or_insert_with(Vec::new)
+            .push(String::from(command));
+        self
+    }
+
+    /// Select this window once the session has launched.
+    pub fn focus(mut self) -> WindowConfig {
+        self.focus = true;
+        self
+    }
+
+    /// Set the size of the layout's main pane, e.g. `65%` or `80c`.
+    pub fn main_size(mut self, size: &str) -> WindowConfig {
+        self.main_size = Some(SizeConfig::from(size));
+        self
+    }
+
+    /// Set the size of each of the layout's columns, from left to right.
+    pub fn column_sizes(mut self, sizes: &[&str]) -> WindowConfig {
+        self.column_sizes = Some(sizes.iter().map(|size| SizeConfig::from(*size)).collect());
+        self
+    }
+
+    /// Set the splits of a `custom` layout. Also sets the layout to `custom`.
+    pub fn tree(mut self, tree: impl Into<SplitTreeConfig>) -> WindowConfig {
+        self.layout = Some(LayoutConfig::Name(String::from("custom")));
+        self.tree = Some(tree.into());
+        self
+    }
+
+    /// Draw the splits of a `custom` layout as ASCII boxes, with each cell
+    /// numbered after the pane it shows. Also sets the layout to `custom`.
+    pub fn diagram(mut self, diagram: &str) -> WindowConfig {
+        self.layout = Some(LayoutConfig::Name(String::from("custom")));
+        self.diagram = Some(String::from(diagram));
+        self
+    }
+
+    /// Set how many columns a `tiled` layout has.
+    pub fn columns(mut self, columns: usize) -> WindowConfig {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Set how many rows a `tiled` layout has.
+    pub fn rows(mut self, rows: usize) -> WindowConfig {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Every layout the window can end up with, along with where it's named
+    /// in the window, e.g. `layout` or `layout[1].layout`.
+    pub fn layout_names(&self) -> Vec<(String, &str)> {
+        match &self.layout {
+            Some(LayoutConfig::Name(name)) => vec![(String::from("layout"), name)],
+            Some(LayoutConfig::Breakpoints(breakpoints)) => breakpoints
+                .iter()
+                .enumerate()
+                .map(|(index, breakpoint)| {
+                    (
+                        format!("layout[{}].layout", index),
+                        breakpoint.layout.as_str(),
+                    )
+                })
+                .collect(),
+            None if self.tree.is_some() || self.diagram.is_some() => {
+                vec![(String::from("layout"), "custom")]
+            }
+            None => vec![(String::from("layout"), "tiled")],
+        }
+    }
+
+    /// Whether the window's layout depends on the size of its first pane.
+    pub fn has_breakpoints(&self) -> bool {
+        matches!(self.layout, Some(LayoutConfig::Breakpoints(_)))
+    }
+
+    /// The name of the window's layout when its first pane is `size`. With
+    /// breakpoints, that's the first one `size` fits, or the last one when
+    /// it fits none of them or isn't known.
+    pub fn layout_name(&self, size: Option<PaneSize>) -> &str {
+        let name = match (&self.layout, size) {
+            (Some(LayoutConfig::Breakpoints(breakpoints)), Some(size)) => breakpoints
+                .iter()
+                .find(|breakpoint| breakpoint.fits(size))
+                .or(breakpoints.last())
+                .map(|breakpoint| breakpoint.layout.as_str()),
+            _ => self.layout_names().last().map(|(_, name)| *name),
+        };
+
+        name.unwrap_or("tiled")
+    }
+
+    /// The window's built-in layout when its first pane is `size`, see
+    /// [`WindowConfig::layout_name`].
+    pub fn parsed_layout(
+        &self,
+        size: Option<PaneSize>,
+    ) -> std::result::Result<Layout, ParseLayoutError> {
+        self.layout_name(size).parse()
+    }
+
+    pub(crate) fn parsed_diagram(
+        &self,
+    ) -> Option<std::result::Result<Diagram, LayoutOptionsError>> {
+        self.diagram.as_ref().map(|diagram| {
+            diagram.parse::<Diagram>().map_err(|e| LayoutOptionsError {
+                option: String::from("diagram"),
+                message: e.to_string(),
+            })
+        })
+    }
+
+    pub fn layout_options(&self) -> std::result::Result<LayoutOptions, LayoutOptionsError> {
+        let size_error = |option: String| {
+            move |error: ParseSizeError| LayoutOptionsError {
+                option,
+                message: error.to_string(),
+            }
+        };
+
+        let main_size = self
+            .main_size
+            .as_ref()
+            .map(SizeConfig::parse)
+            .transpose()
+            .map_err(size_error(String::from("main_size")))?;
+        let column_sizes = self
+            .column_sizes
+            .as_ref()
+            .map(|sizes| {
+                sizes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, size)| {
+                        size.parse()
+                            .map_err(size_error(format!("column_sizes[{}]", index)))
+                    })
+                    .collect()
+            })
+            .transpose()?;
+        let tree = match self.parsed_diagram().transpose()? {
+            Some(diagram) => Some(diagram.tree),
+            None => self
+                .tree
+                .as_ref()
+                .map(|tree| tree.split_tree("tree"))
+                .transpose()?,
+        };
+
+        Ok(LayoutOptions {
+            main_size,
+            column_sizes,
+            tree,
+            columns: self.columns,
+            rows: self.rows,
+        })
+    }
+
+    /// The window's panes, in the order the layout creates them, along with
+    /// where each one is in the window, e.g. `panes[2]`.
+    ///
+    /// They come from the leaves of a custom layout's `tree`, or from
+    /// `panes`, which a `diagram` shows in the order of its numbers.
+    pub fn pane_configs(&self) -> Vec<(String, PaneConfig)> {
+        if let Some(tree) = &self.tree {
+            return tree.panes("tree");
+        }
+
+        if let Some(Ok(diagram)) = self.parsed_diagram() {
+            let panes = self.panes.as_deref().unwrap_or_default();
+
+            // Cells numbered past the end of `panes` are left empty.
+            return diagram
+                .labels
+                .iter()
+                .map(|label| {
+                    let pane = panes
+                        .get(*label)
+                        .cloned()
+                        .unwrap_or(PaneConfig::Options(PaneConfigOptions::new()));
+                    (format!("panes[{}]", label), pane)
+                })
+                .collect();
+        }
+
+        self.panes
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, pane)| (format!("panes[{}]", index), pane.clone()))
+            .collect()
+    }
+
+    pub fn pane_count(&self) -> usize {
+        self.pane_configs().len()
+    }
+
+    /// The directory the window's panes start in, with `~` and environment
+    /// variables expanded and resolved against `base_dir` when relative.
+    pub fn root_dir(
+        &self,
+        base_dir: Option<&Path>,
+    ) -> std::result::Result<Option<String>, RootError> {
+        self.root
+            .as_deref()
+            .map(|root| resolve_root(root, base_dir))
+            .transpose()
+    }
+
+    /// The directory `pane` starts in: its own `root` resolved against the
+    /// window's, or just the window's when it doesn't have one.
+    pub fn pane_root(
+        &self,
+        pane: &PaneConfig,
+        base_dir: Option<&Path>,
+    ) -> std::result::Result<Option<String>, RootError> {
+        let window_root = self.root_dir(base_dir)?;
+
+        match pane.root() {
+            Some(root) => {
+                let base_dir = window_root.as_deref().map(Path::new).or(base_dir);
+                resolve_root(root, base_dir).map(Some)
+            }
+            None => Ok(window_root),
+        }
+    }
+
+    /// Every directory the window's panes start in, the window's `root`
+    /// first, along with where each one is set, e.g. `panes[2].root`.
+    pub fn roots(
+        &self,
+        base_dir: Option<&Path>,
+    ) -> Vec<(String, std::result::Result<String, RootError>)> {
+        let window_root = self
+            .root_dir(base_dir)
+            .transpose()
+            .map(|root| (String::from("root"), root));
+        let pane_roots = self
+            .pane_configs()
+            .into_iter()
+            .filter(|(_, pane)| pane.root().is_some())
+            .filter_map(|(path, pane)| {
+                let root = self.pane_root(&pane, base_dir).transpose()?;
+                Some((format!("{}.root", path), root))
+            });
+
+        window_root.into_iter().chain(pane_roots).collect()
+    }
+
+    /// Whether any of the window's panes has a `root` of its own.
+    pub fn has_pane_roots(&self) -> bool {
+        self.pane_configs()
+            .iter()
+            .any(|(_, pane)| pane.root().is_some())
+    }
+
+    /// Commands run in every pane of the window before the pane's own commands.
+    pub fn window_commands(&self) -> Vec<String> {
+        match (&self.command, &self.commands) {
+            (Some(command), _) => vec![command.clone()],
+            (None, Some(commands)) => commands.clone(),
+            (None, None) => vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PreConfig {
+    Command(String),
+    Commands(Vec<String>),
+}
+
+impl PreConfig {
+    pub fn commands(&self) -> Vec<String> {
+        match self {
+            PreConfig::Command(command) => vec![command.clone()],
+            PreConfig::Commands(commands) => commands.clone(),
+        }
+    }
+}
+
+/// A whole layout file: a session made of windows, which are made of panes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct YAMLConfig {
+    pub name: Option<String>,
+    pub windows: Option<Vec<WindowConfig>>,
+    pub pre: Option<PreConfig>,
+    /// The directory relative `root`s are resolved against. Set to the
+    /// layout file's directory by [`YAMLConfig::from_file`], and left to
+    /// wezterm when it isn't set.
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
+}
+
+impl YAMLConfig {
+    pub fn new() -> YAMLConfig {
+        YAMLConfig::default()
+    }
+
+    /// Read and parse the layout file at `path`.
+    pub fn from_file(path: &str) -> Result<YAMLConfig> {
+        let file = fs::read_to_string(path)
+            .map_err(|_| WeztermocilError::Config(format!("{} not found!", path)))?;
+
+        let config: YAMLConfig = file
+            .parse()
+            .map_err(|e| WeztermocilError::Config(format!("{}: {}", path, e)))?;
+
+        Ok(config.base_dir(&layout_dir(path)?))
+    }
+
+    /// Resolve relative `root`s against `dir`.
+    pub fn base_dir(mut self, dir: &Path) -> YAMLConfig {
+        self.base_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> YAMLConfig {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    pub fn window(mut self, window: WindowConfig) -> YAMLConfig {
+        self.windows.get_or_insert_with(Vec::new).push(window);
+        self
+    }
+
+    /// Add a command to run on the host before any window is created.
+    pub fn pre(mut self, command: &str) -> YAMLConfig {
+        let mut commands = self.pre.map(|pre| pre.commands()).unwrap_or_default();
+        commands.push(String::from(command));
+        self.pre = Some(PreConfig::Commands(commands));
+        self
+    }
+
+    /// Catch mistakes in the config before anything is run or spawned.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with(&LayoutRegistry::new())
+    }
+
+    /// Like [`YAMLConfig::validate`], with the layouts in `layouts` on top
+    /// of the built-in ones.
+    pub fn validate_with(&self, layouts: &LayoutRegistry) -> Result<()> {
+        match self
+            .check_with(layouts)
+            .into_iter()
+            .find(|d| d.severity == Severity::Error)
+        {
+            Some(diagnostic) => Err(WeztermocilError::Config(format!(
+                "{}: {}",
+                diagnostic.path, diagnostic.message
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for YAMLConfig {
+    type Err = WeztermocilError;
+
+    fn from_str(source: &str) -> Result<Self> {
+        serde_yaml::from_str(source).map_err(|e| WeztermocilError::Config(e.to_string()))
+    }
+}
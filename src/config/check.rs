@@ -0,0 +1,339 @@
+use std::{fs, path::Path};
+
+use serde_yaml::Value;
+
+use crate::{
+    diagnostics::{Diagnostic, Location, YamlLocator},
+    layout::{LayoutRegistry, SplitTree},
+};
+
+use super::{layout_dir, BreakpointConfig, LayoutConfig, YAMLConfig};
+
+const SESSION_KEYS: &[&str] = &["name", "windows", "pre"];
+const WINDOW_KEYS: &[&str] = &[
+    "name",
+    "root",
+    "layout",
+    "panes",
+    "command",
+    "commands",
+    "focus",
+    "main_size",
+    "column_sizes",
+    "tree",
+    "diagram",
+    "columns",
+    "rows",
+    "create_root",
+];
+const BREAKPOINT_KEYS: &[&str] = &["min_cols", "min_rows", "layout"];
+const PANE_KEYS: &[&str] = &["commands", "focus", "root"];
+const TREE_KEYS: &[&str] = &["split", "size", "children", "commands", "focus", "root"];
+
+impl YAMLConfig {
+    /// Semantic problems with the config that serde can't catch on its own.
+    pub fn check(&self) -> Vec<Diagnostic> {
+        self.check_with(&LayoutRegistry::new())
+    }
+
+    /// Like [`YAMLConfig::check`], with the layouts in `layouts` on top of
+    /// the built-in ones.
+    pub fn check_with(&self, layouts: &LayoutRegistry) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        let windows = self.windows.as_deref().unwrap_or_default();
+        let mut focused_window = false;
+        let mut focused_pane = false;
+
+        if windows.is_empty() {
+            diagnostics.push(Diagnostic::error("windows", "the layout has no windows"));
+        }
+
+        for (index, window) in windows.iter().enumerate() {
+            let path = format!("windows[{}]", index);
+
+            if window.command.is_some() && window.commands.is_some() {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.commands", path),
+                    "`command` and `commands` are both set, only one of them can be used",
+                ));
+            }
+
+            if window.panes.is_some() && window.tree.is_some() {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.panes", path),
+                    "`panes` can't be combined with `tree`, which already has the window's panes",
+                ));
+            }
+
+            if window.tree.is_some() && window.diagram.is_some() {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.diagram", path),
+                    "`diagram` can't be combined with `tree`, only one of them can describe the splits",
+                ));
+            }
+
+            let options = match window.layout_options() {
+                Ok(options) => Some(options),
+                Err(error) => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("{}.{}", path, error.option),
+                        error.message,
+                    ));
+                    None
+                }
+            };
+
+            let candidates = window.layout_names();
+            if candidates.is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.layout", path),
+                    "there are no breakpoints to pick a layout from",
+                ));
+            }
+            if let Some(LayoutConfig::Breakpoints(breakpoints)) = &window.layout {
+                let catch_all = breakpoints
+                    .iter()
+                    .position(BreakpointConfig::matches_any_size);
+                if let Some(catch_all) = catch_all.filter(|index| index + 1 < breakpoints.len()) {
+                    diagnostics.push(Diagnostic::warning(
+                        format!("{}.layout[{}]", path, catch_all + 1),
+                        format!(
+                            "`layout[{}]` has no minimums and is always picked, so the breakpoints after it are never used",
+                            catch_all
+                        ),
+                    ));
+                }
+            }
+
+            let pane_count = window.pane_count();
+            for (layout_path, name) in &candidates {
+                let layout = match layouts.resolve(name) {
+                    Ok(layout) => layout,
+                    Err(error) => {
+                        diagnostics.push(Diagnostic::error(
+                            format!("{}.{}", path, layout_path),
+                            error.to_string(),
+                        ));
+                        continue;
+                    }
+                };
+
+                if let Some(options) = &options {
+                    if let Err(error) = layout.check_options(options) {
+                        // Trees drawn as a diagram are pointed at through the diagram.
+                        let option = match window.diagram {
+                            Some(_) => error.option.replacen("tree", "diagram", 1),
+                            None => error.option,
+                        };
+                        let message = match window.has_breakpoints() {
+                            true => {
+                                format!("with `{}` from `{}`, {}", name, layout_path, error.message)
+                            }
+                            false => error.message,
+                        };
+                        diagnostics
+                            .push(Diagnostic::error(format!("{}.{}", path, option), message));
+                    }
+                }
+
+                if pane_count > 1 && pane_count < layout.min_panes() {
+                    diagnostics.push(Diagnostic::warning(
+                        format!("{}.panes", path),
+                        format!(
+                            "`{}` always creates {} panes, so {} of them will be left empty",
+                            name,
+                            layout.min_panes(),
+                            layout.min_panes() - pane_count
+                        ),
+                    ));
+                }
+            }
+
+            let cells = options
+                .as_ref()
+                .and_then(|options| options.tree.as_ref())
+                .map(SplitTree::pane_count);
+            if let (Some(cells), Some(_)) = (cells, &window.diagram) {
+                if window
+                    .panes
+                    .as_ref()
+                    .is_some_and(|panes| panes.len() > cells)
+                {
+                    diagnostics.push(Diagnostic::error(
+                        format!("{}.panes[{}]", path, cells),
+                        format!(
+                            "the diagram only has {} cells, so this pane isn't in it",
+                            cells
+                        ),
+                    ));
+                }
+            }
+
+            if let (Some(columns), Some(rows)) = (window.columns, window.rows) {
+                if columns > 0 && rows > 0 && columns * rows < pane_count {
+                    diagnostics.push(Diagnostic::warning(
+                        format!("{}.rows", path),
+                        format!(
+                            "{} columns by {} rows only fit {} of the {} panes, so more rows will be added",
+                            columns,
+                            rows,
+                            columns * rows,
+                            pane_count
+                        ),
+                    ));
+                }
+            }
+
+            for (root_path, root) in window.roots(self.base_dir.as_deref()) {
+                let message = match root {
+                    Err(error) => error.to_string(),
+                    // Missing roots are created before the window is.
+                    Ok(root) if window.create_root && !Path::new(&root).exists() => continue,
+                    Ok(root) if !Path::new(&root).is_dir() => {
+                        format!("`{}` isn't a directory", root)
+                    }
+                    Ok(_) => continue,
+                };
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.{}", path, root_path),
+                    message,
+                ));
+            }
+
+            if window.focus {
+                if focused_window {
+                    diagnostics.push(Diagnostic::error(
+                        format!("{}.focus", path),
+                        "another window is already focused",
+                    ));
+                }
+                focused_window = true;
+            }
+
+            for (pane_path, pane) in window.pane_configs() {
+                if !pane.focus() {
+                    continue;
+                }
+                if focused_pane {
+                    diagnostics.push(Diagnostic::error(
+                        format!("{}.{}.focus", path, pane_path),
+                        "another pane is already focused",
+                    ));
+                }
+                focused_pane = true;
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Keys in the layout file that weztermocil doesn't know about, which
+/// usually means a typo.
+fn check_unknown_keys(document: &Value) -> Vec<Diagnostic> {
+    fn check_keys(value: &Value, path: &str, known: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+        let Some(mapping) = value.as_mapping() else {
+            return;
+        };
+
+        for key in mapping.keys() {
+            let name = key.as_str().unwrap_or_default();
+            if !known.contains(&name) {
+                let key_path = match path.is_empty() {
+                    true => String::from(name),
+                    false => format!("{}.{}", path, name),
+                };
+                diagnostics.push(Diagnostic::error(
+                    key_path,
+                    format!("unknown key `{}`", name),
+                ));
+            }
+        }
+    }
+
+    let mut diagnostics = vec![];
+    check_keys(document, "", SESSION_KEYS, &mut diagnostics);
+
+    let windows = document.get("windows").and_then(Value::as_sequence);
+    for (index, window) in windows.into_iter().flatten().enumerate() {
+        let path = format!("windows[{}]", index);
+        check_keys(window, &path, WINDOW_KEYS, &mut diagnostics);
+
+        let breakpoints = window.get("layout").and_then(Value::as_sequence);
+        for (breakpoint_index, breakpoint) in breakpoints.into_iter().flatten().enumerate() {
+            let breakpoint_path = format!("{}.layout[{}]", path, breakpoint_index);
+            check_keys(
+                breakpoint,
+                &breakpoint_path,
+                BREAKPOINT_KEYS,
+                &mut diagnostics,
+            );
+        }
+
+        let panes = window.get("panes").and_then(Value::as_sequence);
+        for (pane_index, pane) in panes.into_iter().flatten().enumerate() {
+            let pane_path = format!("{}.panes[{}]", path, pane_index);
+            check_keys(pane, &pane_path, PANE_KEYS, &mut diagnostics);
+        }
+
+        let mut nodes = window
+            .get("tree")
+            .map(|tree| (format!("{}.tree", path), tree))
+            .into_iter()
+            .collect::<Vec<_>>();
+        while let Some((node_path, node)) = nodes.pop() {
+            check_keys(node, &node_path, TREE_KEYS, &mut diagnostics);
+
+            let children = node.get("children").and_then(Value::as_sequence);
+            for (child_index, child) in children.into_iter().flatten().enumerate() {
+                nodes.push((format!("{}.children[{}]", node_path, child_index), child));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Every problem with the layout file at `path`, with its location in the file.
+pub fn validate_file(path: &str) -> Vec<Diagnostic> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => return vec![Diagnostic::error("", error.to_string())],
+    };
+
+    let parsed = serde_yaml::from_str::<Value>(&source)
+        .and_then(|document| Ok((serde_yaml::from_str::<YAMLConfig>(&source)?, document)));
+    let (yaml_config, document) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            // serde_yaml appends the location to its messages,
+            // but it's already part of how diagnostics are printed.
+            let message = error.to_string();
+            let message = match message.rfind(" at line ") {
+                Some(end) if !message[end..].contains(',') => String::from(&message[..end]),
+                _ => message,
+            };
+
+            return vec![Diagnostic {
+                location: error.location().map(|l| Location {
+                    line: l.line(),
+                    column: l.column(),
+                }),
+                ..Diagnostic::error("", message)
+            }];
+        }
+    };
+
+    let yaml_config = match layout_dir(path) {
+        Ok(dir) => yaml_config.base_dir(&dir),
+        Err(error) => return vec![Diagnostic::error("", error.to_string())],
+    };
+
+    let mut diagnostics = yaml_config.check();
+    diagnostics.append(&mut check_unknown_keys(&document));
+
+    YamlLocator::new(&source).annotate(&mut diagnostics);
+    diagnostics.sort_by_key(|d| d.location.map(|l| (l.line, l.column)));
+
+    diagnostics
+}
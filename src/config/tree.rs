@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    layout::{LayoutOptionsError, SplitTree},
+    wezterm::pane::SplitDirection,
+};
+
+use super::{PaneConfig, PaneConfigOptions, SizeConfig};
+
+/// A node in the `tree` of a window using the `custom` layout.
+///
+/// Leaves are panes, written either as a bare command or as a hash like the
+/// ones in `panes`. Nodes with `split` divide their space between their
+/// `children` instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SplitTreeConfig {
+    Command(String),
+    Node(SplitNodeConfig),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SplitNodeConfig {
+    pub split: Option<String>,
+    pub size: Option<SizeConfig>,
+    pub children: Option<Vec<SplitTreeConfig>>,
+    pub commands: Option<Vec<String>>,
+    #[serde(default)]
+    pub focus: bool,
+    pub root: Option<String>,
+}
+
+impl SplitNodeConfig {
+    pub fn new() -> SplitNodeConfig {
+        SplitNodeConfig::default()
+    }
+
+    /// Turn the node into a split, `right` to lay its children out left to
+    /// right or `bottom` to lay them out top to bottom.
+    pub fn split(mut self, direction: &str) -> SplitNodeConfig {
+        self.split = Some(String::from(direction));
+        self
+    }
+
+    /// Set the node's size within its parent split, e.g. `70%` or `80c`.
+    pub fn size(mut self, size: &str) -> SplitNodeConfig {
+        self.size = Some(SizeConfig::from(size));
+        self
+    }
+
+    pub fn child(mut self, child: impl Into<SplitTreeConfig>) -> SplitNodeConfig {
+        self.children
+            .get_or_insert_with(Vec::new)
+            .push(child.into());
+        self
+    }
+
+    /// Add a command to run in the pane when it's created.
+    pub fn command(mut self, command: &str) -> SplitNodeConfig {
+        self.commands
+            .get_or_insert_with(Vec::new)
+            .push(String::from(command));
+        self
+    }
+
+    /// Select this pane once the session has launched.
+    pub fn focus(mut self) -> SplitNodeConfig {
+        self.focus = true;
+        self
+    }
+
+    /// Set the directory the pane starts in, relative to the window's `root`.
+    pub fn root(mut self, root: &str) -> SplitNodeConfig {
+        self.root = Some(String::from(root));
+        self
+    }
+}
+
+impl From<&str> for SplitTreeConfig {
+    fn from(command: &str) -> Self {
+        SplitTreeConfig::Command(String::from(command))
+    }
+}
+
+impl From<SplitNodeConfig> for SplitTreeConfig {
+    fn from(node: SplitNodeConfig) -> Self {
+        SplitTreeConfig::Node(node)
+    }
+}
+
+impl SplitTreeConfig {
+    /// The tree's leaves as panes, in the order they're created, along with
+    /// where each of them is in the layout file relative to `path`.
+    pub fn panes(&self, path: &str) -> Vec<(String, PaneConfig)> {
+        match self {
+            SplitTreeConfig::Command(command) => {
+                vec![(String::from(path), PaneConfig::Command(command.clone()))]
+            }
+            SplitTreeConfig::Node(node) => match &node.children {
+                Some(children) => children
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, child)| {
+                        child.panes(&format!("{}.children[{}]", path, index))
+                    })
+                    .collect(),
+                None => vec![(
+                    String::from(path),
+                    PaneConfig::Options(PaneConfigOptions {
+                        commands: node.commands.clone(),
+                        focus: node.focus,
+                        root: node.root.clone(),
+                    }),
+                )],
+            },
+        }
+    }
+
+    /// Resolve the tree into a [`SplitTree`]. `path` is where the tree is in
+    /// the layout file, and is used to point at whatever is wrong with it.
+    pub fn split_tree(&self, path: &str) -> Result<SplitTree, LayoutOptionsError> {
+        let node = match self {
+            SplitTreeConfig::Command(_) => return Ok(SplitTree::Pane { size: None }),
+            SplitTreeConfig::Node(node) => node,
+        };
+        let error = |key: &str, message: &str| LayoutOptionsError {
+            option: format!("{}{}", path, key),
+            message: String::from(message),
+        };
+
+        let size = node
+            .size
+            .as_ref()
+            .map(SizeConfig::parse)
+            .transpose()
+            .map_err(|e| error(".size", &e.to_string()))?;
+
+        let (direction, children) = match (&node.split, &node.children) {
+            (None, None) => return Ok(SplitTree::Pane { size }),
+            (None, Some(_)) => {
+                return Err(error(
+                    ".children",
+                    "`children` need a `split` direction to be laid out in",
+                ))
+            }
+            (Some(_), None) => return Err(error(".split", "a split needs `children`")),
+            (Some(direction), Some(children)) => (direction, children),
+        };
+
+        if node.commands.is_some() || node.focus || node.root.is_some() {
+            return Err(error(
+                "",
+                "a split can't have `commands`, `focus` or `root`, only the panes in it can",
+            ));
+        }
+
+        let direction = match direction.as_str() {
+            "right" => SplitDirection::Right,
+            "bottom" => SplitDirection::Bottom,
+            _ => {
+                return Err(error(
+                    ".split",
+                    &format!(
+                        "unknown split direction `{}`, expected `right` or `bottom`",
+                        direction
+                    ),
+                ))
+            }
+        };
+
+        let children = children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| child.split_tree(&format!("{}.children[{}]", path, index)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SplitTree::Split {
+            size,
+            direction,
+            children,
+        })
+    }
+}
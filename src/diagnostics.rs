@@ -0,0 +1,226 @@
+use std::{collections::HashMap, fmt};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// 1-based position in a layout file.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A problem found in a layout file.
+///
+/// `path` points at the offending node using the same notation serde_yaml
+/// uses in its errors, e.g. `windows[1].layout`, and is resolved to a
+/// [`Location`] with a [`YamlLocator`].
+#[derive(PartialEq, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            path: path.into(),
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(path, message)
+        }
+    }
+
+    /// Render the diagnostic as `file:line:column: severity: message`.
+    pub fn render(&self, file: &str) -> String {
+        let location = match self.location {
+            Some(Location { line, column }) => format!("{}:{}:{}", file, line, column),
+            None => String::from(file),
+        };
+        let path = match self.path.is_empty() {
+            true => String::new(),
+            false => format!("{}: ", self.path),
+        };
+
+        format!("{}: {}: {}{}", location, self.severity, path, self.message)
+    }
+}
+
+/// Maps node paths like `windows[0].panes[2]` to where they start in a
+/// YAML document.
+///
+/// serde_yaml doesn't keep spans once a document is deserialised, so this
+/// does a light pass over the source instead. It understands block mappings
+/// and sequences, which is what layout files are written in, and treats
+/// anything in flow style (`[a, b]`, `{a: b}`) as a single value.
+pub struct YamlLocator {
+    positions: HashMap<String, Location>,
+}
+
+impl YamlLocator {
+    pub fn new(source: &str) -> YamlLocator {
+        let mut positions = HashMap::new();
+        // (column the node starts at, path of the node, whether it's a sequence item)
+        let mut stack: Vec<(usize, String, bool)> = vec![];
+        let mut sequence_lengths: HashMap<String, usize> = HashMap::new();
+        let mut block_scalar_indent: Option<usize> = None;
+
+        for (line_index, line) in source.lines().enumerate() {
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if let Some(scalar_indent) = block_scalar_indent {
+                if indent > scalar_indent {
+                    continue;
+                }
+                block_scalar_indent = None;
+            }
+            if trimmed.starts_with("---") || trimmed.starts_with("...") {
+                continue;
+            }
+
+            let mut column = indent;
+            let mut rest = trimmed;
+
+            loop {
+                let is_item = rest == "-" || rest.starts_with("- ");
+
+                // Anything that started at or after this column has ended. The
+                // one exception is a sequence written at the same indentation
+                // as the key that owns it, which YAML allows.
+                while let Some((start, _, was_item)) = stack.last() {
+                    let owns_sequence = is_item && *start == column && !was_item;
+                    if *start < column || owns_sequence {
+                        break;
+                    }
+                    stack.pop();
+                }
+                let parent = stack
+                    .last()
+                    .map(|(_, path, _)| path.clone())
+                    .unwrap_or_default();
+                let location = Location {
+                    line: line_index + 1,
+                    column: column + 1,
+                };
+
+                if is_item {
+                    let length = sequence_lengths.entry(parent.clone()).or_insert(0);
+                    let path = format!("{}[{}]", parent, length);
+                    *length += 1;
+
+                    positions.insert(path.clone(), location);
+                    stack.push((column, path, true));
+
+                    let after_dash = &rest[1..];
+                    let item = after_dash.trim_start();
+                    if item.is_empty() || item.starts_with('#') {
+                        break;
+                    }
+                    column += 1 + after_dash.len() - item.len();
+                    rest = item;
+                    continue;
+                }
+
+                if let Some((key, value)) = split_key(rest) {
+                    let path = match parent.is_empty() {
+                        true => String::from(key),
+                        false => format!("{}.{}", parent, key),
+                    };
+
+                    positions.insert(path.clone(), location);
+                    stack.push((column, path, false));
+
+                    // The scalar's lines are indented further than its key,
+                    // which isn't the line's indentation for `- key: |`.
+                    if value.starts_with('|') || value.starts_with('>') {
+                        block_scalar_indent = Some(column);
+                    }
+                }
+                break;
+            }
+        }
+
+        YamlLocator { positions }
+    }
+
+    /// Where the node at `path` starts. Falls back to the closest ancestor
+    /// that could be found, since values in flow style aren't tracked.
+    pub fn locate(&self, path: &str) -> Option<Location> {
+        let mut path = path;
+
+        loop {
+            if let Some(location) = self.positions.get(path) {
+                return Some(*location);
+            }
+
+            let parent_end = path.rfind(['.', '['])?;
+            path = &path[..parent_end];
+        }
+    }
+
+    /// Fill in the location of every diagnostic that doesn't have one yet.
+    pub fn annotate(&self, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics.iter_mut() {
+            if diagnostic.location.is_none() {
+                diagnostic.location = self.locate(&diagnostic.path);
+            }
+        }
+    }
+}
+
+/// Split a `key: value` line into its key and (possibly empty) value.
+fn split_key(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with(['[', '{', '"', '\'']) {
+        let quote = line.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let end = line[1..].find(quote)? + 1;
+        let after = line[end + 1..].strip_prefix(':')?;
+        if !after.is_empty() && !after.starts_with(' ') {
+            return None;
+        }
+        return Some((&line[1..end], after.trim()));
+    }
+
+    let mut search_from = 0;
+    while let Some(offset) = line[search_from..].find(':') {
+        let colon = search_from + offset;
+        let after = &line[colon + 1..];
+        if after.is_empty() || after.starts_with(' ') {
+            let key = &line[..colon];
+            if key.contains(" #") {
+                return None;
+            }
+            return Some((key.trim_end(), after.trim()));
+        }
+        search_from = colon + 1;
+    }
+
+    None
+}
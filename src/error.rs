@@ -0,0 +1,102 @@
+use std::{error::Error, fmt, io};
+
+/// Everything that can go wrong while launching a layout.
+///
+/// Each variant maps to its own process exit code (see
+/// [`WeztermocilError::exit_code`]) so scripts wrapping weztermocil can tell
+/// a missing wezterm apart from a broken layout file.
+#[derive(Debug)]
+pub enum WeztermocilError {
+    /// The `wezterm` binary couldn't be found.
+    BinaryMissing { binary: String },
+    /// `wezterm cli` couldn't talk to a running mux.
+    MuxUnreachable { reason: String },
+    /// `wezterm cli` exited with a non-zero status.
+    CommandFailed {
+        command: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// `wezterm cli` succeeded but didn't print a pane id.
+    BadPaneId { output: String },
+    /// The layout file is missing, unreadable or invalid.
+    Config(String),
+    /// A `pre` command exited with a non-zero status.
+    PreFailed {
+        command: String,
+        status: Option<i32>,
+    },
+    /// Any other I/O failure while talking to wezterm.
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, WeztermocilError>;
+
+impl WeztermocilError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WeztermocilError::Io(_) => 1,
+            WeztermocilError::Config(_) => 2,
+            WeztermocilError::BinaryMissing { .. } => 3,
+            WeztermocilError::MuxUnreachable { .. } => 4,
+            WeztermocilError::CommandFailed { .. } => 5,
+            WeztermocilError::BadPaneId { .. } => 6,
+            WeztermocilError::PreFailed { .. } => 7,
+        }
+    }
+}
+
+impl fmt::Display for WeztermocilError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeztermocilError::BinaryMissing { binary } => {
+                write!(
+                    f,
+                    "Couldn't find `{}`. Is wezterm installed and on your PATH?",
+                    binary
+                )
+            }
+            WeztermocilError::MuxUnreachable { reason } => {
+                write!(f, "Couldn't reach a running wezterm: {}", reason)
+            }
+            WeztermocilError::CommandFailed {
+                command,
+                status,
+                stderr,
+            } => {
+                match status {
+                    Some(code) => write!(f, "`{}` exited with status {}", command, code)?,
+                    None => write!(f, "`{}` was terminated by a signal", command)?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, ":\n{}", stderr)?;
+                }
+                Ok(())
+            }
+            WeztermocilError::BadPaneId { output } => {
+                write!(f, "Expected a pane id from wezterm, got {:?}", output)
+            }
+            WeztermocilError::Config(message) => write!(f, "{}", message),
+            WeztermocilError::PreFailed { command, status } => match status {
+                Some(code) => write!(f, "pre command `{}` exited with status {}", command, code),
+                None => write!(f, "pre command `{}` was terminated by a signal", command),
+            },
+            WeztermocilError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for WeztermocilError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WeztermocilError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WeztermocilError {
+    fn from(error: io::Error) -> Self {
+        WeztermocilError::Io(error)
+    }
+}
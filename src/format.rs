@@ -4,3 +4,37 @@ pub fn strip_trailing_newline(input: &str) -> &str {
         .or(input.strip_suffix("\n"))
         .unwrap_or(input)
 }
+
+/// Quote `input` so it can be pasted into a POSIX shell as a single word.
+pub fn shell_quote(input: &str) -> String {
+    let is_plain = !input.is_empty()
+        && input
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./~=:,%+@".contains(c));
+
+    if is_plain {
+        return String::from(input);
+    }
+
+    format!("'{}'", input.replace('\'', r"'\''"))
+}
+
+/// Number of single-character insertions, deletions and substitutions
+/// needed to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b_chars.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+        previous = current;
+    }
+
+    previous[b_chars.len()]
+}
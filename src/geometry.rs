@@ -0,0 +1,295 @@
+use std::{error::Error, fmt};
+
+use crate::wezterm::pane::{Size, SplitDirection};
+
+/// Where a pane is in its tab, in cells from the tab's top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Returned when a split can't be made in a [`Tab`].
+#[derive(PartialEq, Clone, Debug)]
+pub enum SplitError {
+    /// The pane isn't in the tab.
+    UnknownPane(String),
+    /// The pane, or the tab for a top level split, is too small to split.
+    NoRoom {
+        pane_id: String,
+        direction: SplitDirection,
+        size: Size,
+    },
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::UnknownPane(pane_id) => write!(f, "pane {} isn't in the tab", pane_id),
+            SplitError::NoRoom {
+                pane_id,
+                direction,
+                size,
+            } => write!(
+                f,
+                "there's no room to split pane {} {} at {}",
+                pane_id, direction, size
+            ),
+        }
+    }
+}
+
+impl Error for SplitError {}
+
+/// A tab as wezterm models it: a binary tree of splits, each dividing its
+/// space between two sides with a one cell divider in between.
+///
+/// Splits follow `wezterm cli split-pane`. The new pane gets the requested
+/// share of the pane being split, or half of it by default, rounded down
+/// and never less than a cell, and goes on the side given by the direction.
+/// A top level split divides the whole tab instead, and the panes already
+/// in it are resized to fit what's left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tab {
+    columns: usize,
+    rows: usize,
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Pane(String),
+    Split {
+        /// Whether the two sides are next to each other, rather than one
+        /// above the other.
+        side_by_side: bool,
+        /// How long each side is across the split, in cells.
+        first_length: usize,
+        second_length: usize,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Tab {
+    /// A `columns` by `rows` tab holding the single pane `pane_id`.
+    pub fn new(pane_id: &str, columns: usize, rows: usize) -> Tab {
+        Tab {
+            columns,
+            rows,
+            root: Node::Pane(String::from(pane_id)),
+        }
+    }
+
+    pub fn contains(&self, pane_id: &str) -> bool {
+        self.rect(pane_id).is_some()
+    }
+
+    /// Where the pane is, if it's in the tab.
+    pub fn rect(&self, pane_id: &str) -> Option<Rect> {
+        self.rects()
+            .into_iter()
+            .find(|(id, _)| id == pane_id)
+            .map(|(_, rect)| rect)
+    }
+
+    /// Every pane in the tab along with where it is, in the order of the
+    /// tree: left before right and top before bottom within each split.
+    pub fn rects(&self) -> Vec<(String, Rect)> {
+        let mut rects = vec![];
+        self.root.rects(
+            Rect {
+                left: 0,
+                top: 0,
+                width: self.columns,
+                height: self.rows,
+            },
+            &mut rects,
+        );
+
+        rects
+    }
+
+    /// Split `pane_id` the way `wezterm cli split-pane` would, adding
+    /// `new_pane_id` on the `direction` side.
+    pub fn split(
+        &mut self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        new_pane_id: &str,
+    ) -> Result<(), SplitError> {
+        let rect = self
+            .rect(pane_id)
+            .ok_or_else(|| SplitError::UnknownPane(String::from(pane_id)))?;
+        let size = size.unwrap_or(Size::Percent(50));
+        let side_by_side = matches!(direction, SplitDirection::Right | SplitDirection::Left);
+
+        let length = match (top_level, side_by_side) {
+            (true, true) => self.columns,
+            (true, false) => self.rows,
+            (false, true) => rect.width,
+            (false, false) => rect.height,
+        };
+        let (kept, created) = split_length(length, size).ok_or_else(|| SplitError::NoRoom {
+            pane_id: String::from(pane_id),
+            direction: *direction,
+            size,
+        })?;
+
+        let target = match top_level {
+            true => &mut self.root,
+            false => self.root.find_mut(pane_id).unwrap(),
+        };
+        let mut existing = std::mem::replace(target, Node::Pane(String::new()));
+        if top_level {
+            existing.resize(side_by_side, kept as isize - length as isize);
+        }
+        let new_pane = Node::Pane(String::from(new_pane_id));
+
+        *target = match direction {
+            SplitDirection::Right | SplitDirection::Bottom => Node::Split {
+                side_by_side,
+                first_length: kept,
+                second_length: created,
+                first: Box::new(existing),
+                second: Box::new(new_pane),
+            },
+            SplitDirection::Left | SplitDirection::Top => Node::Split {
+                side_by_side,
+                first_length: created,
+                second_length: kept,
+                first: Box::new(new_pane),
+                second: Box::new(existing),
+            },
+        };
+
+        Ok(())
+    }
+}
+
+impl Node {
+    fn rects(&self, rect: Rect, rects: &mut Vec<(String, Rect)>) {
+        match self {
+            Node::Pane(pane_id) => rects.push((pane_id.clone(), rect)),
+            Node::Split {
+                side_by_side: true,
+                first_length,
+                second_length,
+                first,
+                second,
+            } => {
+                let first_rect = Rect {
+                    width: *first_length,
+                    ..rect
+                };
+                let second_rect = Rect {
+                    left: rect.left + first_length + 1,
+                    width: *second_length,
+                    ..rect
+                };
+                first.rects(first_rect, rects);
+                second.rects(second_rect, rects);
+            }
+            Node::Split {
+                side_by_side: false,
+                first_length,
+                second_length,
+                first,
+                second,
+            } => {
+                let first_rect = Rect {
+                    height: *first_length,
+                    ..rect
+                };
+                let second_rect = Rect {
+                    top: rect.top + first_length + 1,
+                    height: *second_length,
+                    ..rect
+                };
+                first.rects(first_rect, rects);
+                second.rects(second_rect, rects);
+            }
+        }
+    }
+
+    fn find_mut(&mut self, pane_id: &str) -> Option<&mut Node> {
+        match self {
+            Node::Pane(id) if id == pane_id => Some(self),
+            Node::Pane(_) => None,
+            Node::Split { first, second, .. } => {
+                first.find_mut(pane_id).or_else(|| second.find_mut(pane_id))
+            }
+        }
+    }
+
+    /// Grow or shrink the node by `delta` cells across (`side_by_side`) or
+    /// down. Like wezterm, a split takes the change a cell at a time from
+    /// alternating sides, never leaving a side with less than a cell.
+    fn resize(&mut self, side_by_side: bool, delta: isize) {
+        let Node::Split {
+            side_by_side: split_side_by_side,
+            first_length,
+            second_length,
+            first,
+            second,
+        } = self
+        else {
+            return;
+        };
+
+        // The change runs along both sides of a split the other way.
+        if *split_side_by_side != side_by_side {
+            first.resize(side_by_side, delta);
+            second.resize(side_by_side, delta);
+            return;
+        }
+
+        let (mut first_delta, mut second_delta) = (0, 0);
+        let mut remaining = delta;
+        while remaining > 0 {
+            first_delta += 1;
+            remaining -= 1;
+            if remaining > 0 {
+                second_delta += 1;
+                remaining -= 1;
+            }
+        }
+        while remaining < 0 {
+            let shrunk = remaining;
+            if *first_length as isize + first_delta > 1 {
+                first_delta -= 1;
+                remaining += 1;
+            }
+            if remaining < 0 && *second_length as isize + second_delta > 1 {
+                second_delta -= 1;
+                remaining += 1;
+            }
+            if remaining == shrunk {
+                break;
+            }
+        }
+
+        *first_length = (*first_length as isize + first_delta) as usize;
+        *second_length = (*second_length as isize + second_delta) as usize;
+        first.resize(side_by_side, first_delta);
+        second.resize(side_by_side, second_delta);
+    }
+}
+
+/// Split `length` cells into what the split pane keeps and what the new
+/// pane gets, leaving a cell for the divider. `None` when the pane being
+/// split would be left without any cells.
+fn split_length(length: usize, size: Size) -> Option<(usize, usize)> {
+    let created = match size {
+        Size::Percent(percent) => length * percent as usize / 100,
+        Size::Cells(cells) => cells,
+    }
+    .max(1);
+    let kept = length.checked_sub(created + 1).filter(|kept| *kept > 0)?;
+
+    Some((kept, created))
+}
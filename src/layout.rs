@@ -1,173 +1,1068 @@
-use std::fmt;
+use std::{error::Error, fmt, str::FromStr};
 
-use crate::wezterm::pane::{Pane, SplitDirection};
+use crate::{
+    error::{Result, WeztermocilError},
+    format::edit_distance,
+    multiplexer::{Multiplexer, TrackingBackend},
+    wezterm::pane::{Pane, Size, SplitDirection},
+};
+
+mod diagram;
+mod strategy;
+mod tree;
+
+pub use diagram::{Diagram, ParseDiagramError};
+pub use strategy::{LayoutRegistry, LayoutStrategy};
+pub use tree::SplitTree;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct TotalPanes(pub usize);
 
 impl fmt::Display for TotalPanes {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        write!(f, "{}", self.0)
     }
 }
 
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Layout {
     EvenHorizontal,
     EvenVertical,
     MainVertical,
     MainVerticalFlipped,
+    MainHorizontal,
+    MainHorizontalFlipped,
     Tiled,
     ThreeColumns,
     DoubleMainHorizontal,
     DoubleMainVertical,
+    /// A fixed number of columns, with the panes stacked evenly in them.
+    Columns(usize),
+    /// A fixed number of rows, with the panes side by side in them.
+    Rows(usize),
+    /// Each pane takes half of the space the one before it left, turning
+    /// right, down, left and up around the tab.
+    Spiral,
+    /// A main column in the middle with a stack of panes on either side.
+    CenteredMain,
+    /// Splits described by the window's [`SplitTree`].
+    Custom,
+}
+
+/// Returned when parsing a name that isn't one of [`Layout::NAMES`].
+#[derive(PartialEq, Clone, Debug)]
+pub struct ParseLayoutError {
+    pub name: String,
+    pub suggestion: Option<&'static str>,
+}
+
+impl fmt::Display for ParseLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown layout `{}`", self.name)?;
+        match self.suggestion {
+            Some(suggestion) => write!(f, ", did you mean `{}`?", suggestion)?,
+            None => write!(f, ".")?,
+        }
+        write!(f, " Valid layouts are: {}", Layout::NAMES.join(", "))
+    }
+}
+
+impl Error for ParseLayoutError {}
+
+/// What a window can ask of its layout, on top of how many panes it has.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct LayoutOptions {
+    /// The size of the main pane of the `main-*` layouts, of the main row of
+    /// `double-main-horizontal`, of each main column of `double-main-vertical`,
+    /// and of the middle column of `centered-main`.
+    pub main_size: Option<Size>,
+    /// The size of each column of `3_columns`, `double-main-vertical`,
+    /// `centered-main` and `columns:N`, from left to right.
+    pub column_sizes: Option<Vec<Size>>,
+    /// The splits making up a `custom` layout.
+    pub tree: Option<SplitTree>,
+    /// How many columns and rows `tiled` lays its panes out in, instead of
+    /// working them out from the number of panes.
+    pub columns: Option<usize>,
+    pub rows: Option<usize>,
+}
+
+impl LayoutOptions {
+    /// The first option that's set, as it's spelled in a layout file.
+    pub fn first_set(&self) -> Option<&'static str> {
+        [
+            ("main_size", self.main_size.is_some()),
+            ("column_sizes", self.column_sizes.is_some()),
+            ("tree", self.tree.is_some()),
+            ("columns", self.columns.is_some()),
+            ("rows", self.rows.is_some()),
+        ]
+        .into_iter()
+        .find(|(_, set)| *set)
+        .map(|(option, _)| option)
+    }
+}
+
+/// Returned when [`LayoutOptions`] don't make sense for a layout.
+#[derive(PartialEq, Clone, Debug)]
+pub struct LayoutOptionsError {
+    /// The option at fault, as it's spelled in a layout file, e.g.
+    /// `main_size` or `tree.children[1].size`.
+    pub option: String,
+    pub message: String,
+}
+
+impl fmt::Display for LayoutOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.option, self.message)
+    }
+}
+
+impl Error for LayoutOptionsError {}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Layout::Columns(count) => return write!(f, "columns:{}", count),
+            Layout::Rows(count) => return write!(f, "rows:{}", count),
+            Layout::Spiral => "spiral",
+            Layout::CenteredMain => "centered-main",
+            Layout::Tiled => "tiled",
+            Layout::EvenHorizontal => "even-horizontal",
+            Layout::EvenVertical => "even-vertical",
+            Layout::MainVertical => "main-vertical",
+            Layout::MainVerticalFlipped => "main-vertical-flipped",
+            Layout::MainHorizontal => "main-horizontal",
+            Layout::MainHorizontalFlipped => "main-horizontal-flipped",
+            Layout::ThreeColumns => "3_columns",
+            Layout::DoubleMainHorizontal => "double-main-horizontal",
+            Layout::DoubleMainVertical => "double-main-vertical",
+            Layout::Custom => "custom",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Layout {
+    type Err = ParseLayoutError;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        let count = |count: &str| count.parse().ok().filter(|count| *count > 0);
+        let parameterized = match name.split_once(':') {
+            Some(("columns", value)) => count(value).map(Layout::Columns),
+            Some(("rows", value)) => count(value).map(Layout::Rows),
+            _ => None,
+        };
+        if let Some(layout) = parameterized {
+            return Ok(layout);
+        }
+
+        match name {
+            "tiled" => Ok(Layout::Tiled),
+            "even-horizontal" => Ok(Layout::EvenHorizontal),
+            "main-vertical" => Ok(Layout::MainVertical),
+            "main-vertical-flipped" => Ok(Layout::MainVerticalFlipped),
+            "main-horizontal" => Ok(Layout::MainHorizontal),
+            "main-horizontal-flipped" => Ok(Layout::MainHorizontalFlipped),
+            "even-vertical" => Ok(Layout::EvenVertical),
+            "3_columns" => Ok(Layout::ThreeColumns),
+            "double-main-horizontal" => Ok(Layout::DoubleMainHorizontal),
+            "double-main-vertical" => Ok(Layout::DoubleMainVertical),
+            "spiral" => Ok(Layout::Spiral),
+            "centered-main" => Ok(Layout::CenteredMain),
+            "custom" => Ok(Layout::Custom),
+            _ => Err(ParseLayoutError {
+                name: String::from(name),
+                suggestion: Layout::suggest(name),
+            }),
+        }
+    }
 }
 
 impl Layout {
-    pub fn create(&self, total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-        if total_panes == TotalPanes(1) {
-            // Skip doing any pane creation
-            // if there's only 1 pane being passed.
-            // We can just run the command in the
-            // main pane
-            return None;
+    /// Every name accepted by [`Layout::from_str`], with `N` standing for
+    /// the count a layout takes.
+    pub const NAMES: &'static [&'static str] = &[
+        "tiled",
+        "even-horizontal",
+        "even-vertical",
+        "main-vertical",
+        "main-vertical-flipped",
+        "main-horizontal",
+        "main-horizontal-flipped",
+        "3_columns",
+        "double-main-horizontal",
+        "double-main-vertical",
+        "columns:N",
+        "rows:N",
+        "spiral",
+        "centered-main",
+        "custom",
+    ];
+
+    /// A line on how the layout named `name`, one of [`Layout::NAMES`],
+    /// arranges its panes.
+    pub fn describe(name: &str) -> Option<&'static str> {
+        let description = match name {
+            "tiled" => "A grid close to square, or `columns` by `rows`",
+            "even-horizontal" => "Side by side, all the same width",
+            "even-vertical" => "On top of each other, all the same height",
+            "main-vertical" => "A main pane on the left, the rest stacked on the right",
+            "main-vertical-flipped" => "A main pane on the right, the rest stacked on the left",
+            "main-horizontal" => "A main pane on top, the rest side by side below it",
+            "main-horizontal-flipped" => {
+                "A main pane at the bottom, the rest side by side above it"
+            }
+            "3_columns" => "Three columns, with the rest of the panes stacked in them",
+            "double-main-horizontal" => {
+                "Two main panes at the bottom, the rest side by side above them"
+            }
+            "double-main-vertical" => {
+                "Two main columns, the rest stacked in a third one on the right"
+            }
+            "columns:N" => "N columns, with the panes stacked evenly in them",
+            "rows:N" => "N rows, with the panes side by side in them",
+            "spiral" => "Each pane takes half of what the one before it left, spiralling inwards",
+            "centered-main" => "A main column in the middle, the rest stacked on either side",
+            "custom" => "The splits given by the window's `tree` or `diagram`",
+            _ => return None,
+        };
+
+        Some(description)
+    }
+
+    /// The fewest panes the layout creates. Configuring fewer than
+    /// this leaves the extra panes without any commands.
+    pub fn min_panes(&self) -> usize {
+        match self {
+            Layout::ThreeColumns
+            | Layout::DoubleMainHorizontal
+            | Layout::DoubleMainVertical
+            | Layout::CenteredMain => 3,
+            Layout::Columns(count) | Layout::Rows(count) => *count,
+            _ => 1,
         }
+    }
 
+    /// Whether the layout has a main pane, or main panes, that `main_size` applies to.
+    pub fn has_main_pane(&self) -> bool {
+        matches!(
+            self,
+            Layout::MainVertical
+                | Layout::MainVerticalFlipped
+                | Layout::MainHorizontal
+                | Layout::MainHorizontalFlipped
+                | Layout::DoubleMainHorizontal
+                | Layout::DoubleMainVertical
+                | Layout::CenteredMain
+        )
+    }
+
+    /// How many columns `column_sizes` has to size, for layouts with a fixed
+    /// number of them.
+    pub fn column_count(&self) -> Option<usize> {
         match self {
-            Layout::EvenHorizontal => even_horizontal(total_panes, starting_pane.clone()),
-            Layout::EvenVertical => even_vertical(total_panes, starting_pane.clone()),
-            Layout::MainVertical => main_vertical(total_panes, starting_pane.clone()),
+            Layout::ThreeColumns | Layout::DoubleMainVertical | Layout::CenteredMain => Some(3),
+            Layout::Columns(count) => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// The sizes `main_size` gives the columns of layouts with main columns,
+    /// in the order they're passed to [`split_sized`].
+    fn main_column_sizes(&self, main_size: Size) -> Option<Vec<Option<Size>>> {
+        match self {
+            // Split off to the left, away from the last column.
+            Layout::DoubleMainVertical => Some(vec![None, Some(main_size), Some(main_size)]),
+            Layout::CenteredMain => Some(vec![None, Some(main_size), None]),
+            _ => None,
+        }
+    }
+
+    /// Check that `options` can be applied to the layout.
+    pub fn check_options(
+        &self,
+        options: &LayoutOptions,
+    ) -> std::result::Result<(), LayoutOptionsError> {
+        let error = |option: &str, message: String| {
+            Err(LayoutOptionsError {
+                option: String::from(option),
+                message,
+            })
+        };
+
+        match (self, &options.tree) {
+            (Layout::Custom, Some(tree)) => tree.check("tree")?,
+            (Layout::Custom, None) => {
+                return error(
+                    "tree",
+                    String::from("the `custom` layout needs a `tree` or a `diagram`"),
+                );
+            }
+            (_, Some(_)) => {
+                return error(
+                    "tree",
+                    String::from("only the `custom` layout can have a `tree` or a `diagram`"),
+                );
+            }
+            (_, None) => {}
+        }
+
+        for (option, count) in [("columns", options.columns), ("rows", options.rows)] {
+            match count {
+                Some(_) if *self != Layout::Tiled => {
+                    return error(
+                        option,
+                        format!("only the `tiled` layout can have `{}`", option),
+                    );
+                }
+                Some(0) => return error(option, format!("`{}` has to be at least 1", option)),
+                _ => {}
+            }
+        }
+
+        if let Some(main_size) = options.main_size {
+            if !self.has_main_pane() {
+                return error(
+                    "main_size",
+                    String::from("the layout doesn't have a main pane"),
+                );
+            }
+            if options.column_sizes.is_some() {
+                return error(
+                    "main_size",
+                    String::from("`main_size` and `column_sizes` can't be combined"),
+                );
+            }
+            // The same sizes the layout is split by, so whatever passes here
+            // can be split on any tab with room for the percentages.
+            let fits = self
+                .main_column_sizes(main_size)
+                .is_none_or(|sizes| check_sizes(&sizes).is_ok());
+            if !fits {
+                let message = match self {
+                    Layout::DoubleMainVertical => format!(
+                        "two main columns of {} leave no room for the last column",
+                        main_size
+                    ),
+                    _ => format!(
+                        "a main column of {} leaves no room for the columns beside it",
+                        main_size
+                    ),
+                };
+                return error("main_size", message);
+            }
+        }
+
+        if let Some(sizes) = &options.column_sizes {
+            let Some(count) = self.column_count() else {
+                return error(
+                    "column_sizes",
+                    String::from("the layout doesn't have a fixed number of columns"),
+                );
+            };
+            if sizes.len() != count {
+                return error(
+                    "column_sizes",
+                    format!(
+                        "expected {} sizes, one per column, got {}",
+                        count,
+                        sizes.len()
+                    ),
+                );
+            }
+
+            let sizes = sizes.iter().copied().map(Some).collect::<Vec<_>>();
+            if let Err(message) = check_sizes(&sizes) {
+                return error("column_sizes", message);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The closest valid layout name to `name`, if any is close enough
+    /// to plausibly be a typo.
+    fn suggest(name: &str) -> Option<&'static str> {
+        let max_distance = (name.len() / 3).max(2);
+
+        Layout::NAMES
+            .iter()
+            .map(|candidate| (edit_distance(name, candidate), *candidate))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate)
+    }
+
+    pub fn create(
+        &self,
+        backend: &dyn Multiplexer,
+        total_panes: TotalPanes,
+        starting_pane: Pane,
+        options: &LayoutOptions,
+    ) -> Result<Vec<Pane>> {
+        self.check_options(options)
+            .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+
+        if options.tree.is_none() && total_panes.0 <= 1 {
+            // Skip doing any pane creation
+            // if there's at most 1 pane being passed.
+            // The starting pane is the only pane
+            // and commands run in it directly.
+            return Ok(vec![starting_pane]);
+        }
+
+        // Splits are worked out in cells when the starting pane's size is
+        // known, and the tracked tab answers for every pane split off it
+        // without asking the multiplexer again.
+        let tracked = match backend.pane_size(&starting_pane.id)? {
+            Some(size) => TrackingBackend::new(backend, size.columns, size.rows),
+            None => TrackingBackend::relative(backend),
+        };
+        let backend = &tracked;
+
+        // Custom layouts get their panes from the tree, not from `total_panes`.
+        if let Some(tree) = &options.tree {
+            return tree.create(backend, starting_pane);
+        }
+        let panes = match self {
+            Layout::EvenHorizontal => even_horizontal(backend, total_panes, starting_pane.clone()),
+            Layout::EvenVertical => even_vertical(backend, total_panes, starting_pane.clone()),
+            Layout::MainVertical => {
+                main_vertical(backend, total_panes, starting_pane.clone(), options)
+            }
             Layout::MainVerticalFlipped => {
-                main_vertical_flipped(total_panes, starting_pane.clone())
+                main_vertical_flipped(backend, total_panes, starting_pane.clone(), options)
+            }
+            Layout::MainHorizontal => {
+                main_horizontal(backend, total_panes, starting_pane.clone(), options)
+            }
+            Layout::MainHorizontalFlipped => {
+                main_horizontal_flipped(backend, total_panes, starting_pane.clone(), options)
+            }
+            Layout::Tiled => tiled(backend, total_panes, starting_pane.clone(), options),
+            Layout::ThreeColumns => {
+                three_columns(backend, total_panes, starting_pane.clone(), options)
+            }
+            Layout::DoubleMainVertical => {
+                double_main_vertical(backend, total_panes, starting_pane.clone(), options)
             }
-            Layout::Tiled => tiled(total_panes, starting_pane.clone()),
-            Layout::ThreeColumns => three_columns(total_panes, starting_pane.clone()),
-            Layout::DoubleMainVertical => double_main_vertical(total_panes, starting_pane.clone()),
             Layout::DoubleMainHorizontal => {
-                double_main_horizontal(total_panes, starting_pane.clone())
+                double_main_horizontal(backend, total_panes, starting_pane.clone(), options)
+            }
+            Layout::Columns(count) => {
+                columns(backend, total_panes, starting_pane.clone(), *count, options)
+            }
+            Layout::Rows(count) => rows(backend, total_panes, starting_pane.clone(), *count),
+            Layout::Spiral => spiral(backend, total_panes, starting_pane.clone()),
+            Layout::CenteredMain => {
+                centered_main(backend, total_panes, starting_pane.clone(), options)
             }
+            Layout::Custom => unreachable!("custom layouts are created from their tree"),
+        }?;
+
+        Ok(self.reading_order(&tracked, panes))
+    }
+
+    /// Sort the panes a layout created into the order `panes` fills them
+    /// in, going by where they ended up rather than how they were split:
+    ///
+    /// - `even-horizontal`, `3_columns`, `double-main-vertical` and
+    ///   `columns:N` go column by column, from left to right and top to
+    ///   bottom within a column.
+    /// - `even-vertical`, `tiled`, `double-main-horizontal` and `rows:N` go
+    ///   row by row, from top to bottom and left to right within a row.
+    /// - The `main-*` layouts start with the main pane, then go through
+    ///   the rest row by row.
+    /// - `centered-main` starts with the main pane, then goes through the
+    ///   stacks column by column.
+    /// - `spiral` goes from the outside in, the order its panes are split.
+    fn reading_order(&self, backend: &TrackingBackend, mut panes: Vec<Pane>) -> Vec<Pane> {
+        let position = |pane: &Pane| backend.rect(&pane.id).map(|rect| (rect.left, rect.top));
+
+        match self {
+            Layout::EvenHorizontal
+            | Layout::ThreeColumns
+            | Layout::DoubleMainVertical
+            | Layout::Columns(_) => {
+                panes.sort_by_key(position);
+            }
+            Layout::EvenVertical
+            | Layout::Tiled
+            | Layout::DoubleMainHorizontal
+            | Layout::Rows(_) => {
+                panes.sort_by_key(|pane| position(pane).map(|(left, top)| (top, left)));
+            }
+            Layout::CenteredMain => {
+                // The main pane is always created first.
+                panes[1..].sort_by_key(position);
+            }
+            Layout::MainVertical
+            | Layout::MainVerticalFlipped
+            | Layout::MainHorizontal
+            | Layout::MainHorizontalFlipped => {
+                // The main pane is always created first.
+                panes[1..].sort_by_key(|pane| position(pane).map(|(left, top)| (top, left)));
+            }
+            Layout::Spiral | Layout::Custom => {}
         }
+
+        panes
     }
 }
 
+/// Split `starting_pane` into `total_panes` panes of the same size along
+/// `direction`. When the backend can't tell how big `starting_pane` is,
+/// sizes are rounded percentages, which can leave panes a few cells apart.
 fn split_even(
+    backend: &dyn Multiplexer,
     total_panes: TotalPanes,
     starting_pane: Pane,
     direction: SplitDirection,
-) -> Option<Vec<Pane>> {
+) -> Result<Vec<Pane>> {
+    if backend.pane_size(&starting_pane.id)?.is_some() {
+        let sizes = vec![None; total_panes.0.max(1)];
+        return split_sized(backend, starting_pane, direction, &sizes);
+    }
+
     let mut panes: Vec<Pane> = vec![starting_pane.clone()];
 
     let remaining_panes_count = total_panes.0 - panes.len();
 
     if remaining_panes_count == 0 {
-        return Some(panes);
+        return Ok(panes);
     }
 
     // If there's one other pane to create, split parent once at 50% and return
     if remaining_panes_count == 1 {
-        let pane = starting_pane.split(&direction, Some("50"), None, false);
+        let pane =
+            starting_pane.split(backend, &direction, Some(Size::Percent(50)), None, false)?;
         panes.push(pane);
-        return Some(panes);
+        return Ok(panes);
     }
 
     for p in 0..remaining_panes_count {
-        let pane_perc = ((1.0 / (total_panes.0 - p) as f32) * 100.0)
-            .round()
-            .to_string();
-        let pane = starting_pane.split(&direction, Some(&pane_perc), None, false);
+        let pane_perc = ((1.0 / (total_panes.0 - p) as f32) * 100.0).round() as u8;
+        let pane = starting_pane.split(
+            backend,
+            &direction,
+            Some(Size::Percent(pane_perc)),
+            None,
+            false,
+        )?;
         panes.push(pane);
     }
 
-    Some(panes)
+    Ok(panes)
 }
 
+/// Split off a main pane and stack the rest of the panes evenly next to it.
+/// `direction` is where the stack goes relative to the main pane and
+/// `stack_direction` is how the stack itself is split.
 fn main_splits(
+    backend: &dyn Multiplexer,
     total_panes: TotalPanes,
     starting_pane: Pane,
     direction: SplitDirection,
-) -> Option<Vec<Pane>> {
-    let main_pane = starting_pane.split(&direction, Some("50"), None, false);
+    stack_direction: SplitDirection,
+    main_size: Option<Size>,
+) -> Result<Vec<Pane>> {
+    let (main_pane, stack_pane) = match main_size {
+        // A split only sets the size of the pane it creates. For percentages
+        // that's easy to flip around, but for cells the main pane has to be
+        // the new one, so split towards the other side.
+        Some(Size::Cells(cells)) => {
+            let main_pane = starting_pane.split(
+                backend,
+                &direction.opposite(),
+                Some(Size::Cells(cells)),
+                None,
+                false,
+            )?;
+            (main_pane, starting_pane)
+        }
+        Some(Size::Percent(percent)) => {
+            let stack_pane = starting_pane.split(
+                backend,
+                &direction,
+                Some(Size::Percent(100 - percent)),
+                None,
+                false,
+            )?;
+            (starting_pane, stack_pane)
+        }
+        None => {
+            let stack_pane =
+                starting_pane.split(backend, &direction, Some(Size::Percent(50)), None, false)?;
+            (starting_pane, stack_pane)
+        }
+    };
 
-    match split_even(
+    let mut panes = split_even(
+        backend,
         TotalPanes(total_panes.0 - 1),
-        main_pane.clone(),
-        SplitDirection::Bottom,
-    ) {
-        Some(mut panes) => {
-            let mut all_panes = vec![starting_pane.clone()];
-            all_panes.append(&mut panes);
-            Some(all_panes)
+        stack_pane,
+        stack_direction,
+    )?;
+
+    let mut all_panes = vec![main_pane];
+    all_panes.append(&mut panes);
+    Ok(all_panes)
+}
+
+/// Split `starting_pane` into one pane per entry in `sizes` along `direction`.
+/// The panes are returned by how far they are from `starting_pane`, which
+/// comes first.
+///
+/// Panes are peeled off the far end, so each split only needs the size of
+/// the pane it creates and `starting_pane` keeps whatever is left. When the
+/// backend knows how big `starting_pane` is, every size is worked out in
+/// cells up front with [`cell_sizes`]. Otherwise each percentage is made
+/// relative to what's left of `starting_pane`, see [`relative_sizes`].
+fn split_sized(
+    backend: &dyn Multiplexer,
+    starting_pane: Pane,
+    direction: SplitDirection,
+    sizes: &[Option<Size>],
+) -> Result<Vec<Pane>> {
+    let sizes = match backend.pane_size(&starting_pane.id)? {
+        Some(size) => cell_sizes(size.along(direction), sizes),
+        None => relative_sizes(sizes),
+    }
+    .map_err(WeztermocilError::Config)?;
+
+    let mut remaining_percent = 100;
+    let mut peeled = vec![];
+
+    for size in sizes.iter().skip(1).rev() {
+        let size = match *size {
+            Size::Percent(percent) => {
+                let relative = (percent as f32 / remaining_percent as f32 * 100.0).round();
+                remaining_percent -= percent;
+                Size::Percent(relative as u8)
+            }
+            cells => cells,
+        };
+        peeled.push(starting_pane.split(backend, &direction, Some(size), None, false)?);
+    }
+
+    let mut panes = vec![starting_pane];
+    panes.extend(peeled.into_iter().rev());
+    Ok(panes)
+}
+
+/// Check that `sizes`, for panes split along the same direction, add up to
+/// no more than the pane being split, whatever its size.
+fn check_sizes(sizes: &[Option<Size>]) -> std::result::Result<(), String> {
+    let total = sizes
+        .iter()
+        .map(|size| match size {
+            Some(Size::Percent(percent)) => *percent as usize,
+            _ => 0,
+        })
+        .sum::<usize>();
+    if total > 100 {
+        return Err(format!("percentages add up to {}%, more than 100%", total));
+    }
+
+    let has_cells = sizes
+        .iter()
+        .any(|size| matches!(size, Some(Size::Cells(_))));
+    let unsized_count = sizes.iter().filter(|size| size.is_none()).count();
+    if !has_cells && unsized_count > 0 && (100 - total) / unsized_count == 0 {
+        return Err(format!(
+            "percentages add up to {}%, which leaves no room for the children without a size",
+            total
+        ));
+    }
+
+    Ok(())
+}
+
+/// Work out how many cells each pane in `sizes` gets when a pane `length`
+/// cells long is split between them, leaving a cell for each divider.
+///
+/// Percentages are of the cells the panes share. Panes without a size split
+/// what's left evenly, the first ones getting a cell more when it doesn't
+/// divide evenly, and when every pane has a size the first one takes
+/// whatever is left.
+fn cell_sizes(length: usize, sizes: &[Option<Size>]) -> std::result::Result<Vec<Size>, String> {
+    let no_room = || format!("{} panes don't fit in {} cells", sizes.len(), length);
+    let shared = length
+        .checked_sub(sizes.len().saturating_sub(1))
+        .ok_or_else(no_room)?;
+
+    let mut cells = sizes
+        .iter()
+        .map(|size| match size {
+            Some(Size::Percent(percent)) => shared * *percent as usize / 100,
+            Some(Size::Cells(cells)) => *cells,
+            None => 0,
+        })
+        .collect::<Vec<_>>();
+    let total = cells.iter().sum::<usize>();
+    let left = shared.checked_sub(total).ok_or_else(|| {
+        format!(
+            "sizes add up to {} cells, more than the {} there's room for",
+            total, shared
+        )
+    })?;
+
+    let unsized_panes = (0..sizes.len())
+        .filter(|index| sizes[*index].is_none())
+        .collect::<Vec<_>>();
+    match unsized_panes.len() {
+        0 => cells[0] += left,
+        count => {
+            for (nth, index) in unsized_panes.into_iter().enumerate() {
+                cells[index] = left / count + usize::from(nth < left % count);
+            }
         }
-        None => Some(vec![starting_pane.clone()]),
     }
+
+    if cells.contains(&0) {
+        return Err(no_room());
+    }
+
+    Ok(cells.into_iter().map(Size::Cells).collect())
 }
 
-fn even_horizontal(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-    split_even(total_panes, starting_pane, SplitDirection::Right)
+/// Work out sizes for [`split_sized`] without knowing how big the pane
+/// being split is.
+///
+/// Sizes are then either all percentages or all cells. With percentages,
+/// panes without a size share what's left evenly. With cells, the first
+/// pane takes whatever is left, so it's the only one that can go without a
+/// size.
+fn relative_sizes(sizes: &[Option<Size>]) -> std::result::Result<Vec<Size>, String> {
+    check_sizes(sizes)?;
+
+    let has_cells = sizes
+        .iter()
+        .any(|size| matches!(size, Some(Size::Cells(_))));
+    if has_cells {
+        if sizes
+            .iter()
+            .any(|size| matches!(size, Some(Size::Percent(_))))
+        {
+            return Err(String::from(
+                "sizes in a split have to be either all percentages or all cells when the size of the pane being split isn't known",
+            ));
+        }
+        if sizes.iter().skip(1).any(Option::is_none) {
+            return Err(String::from(
+                "with cells, every child but the first needs a size when the size of the pane being split isn't known, the first one takes whatever is left",
+            ));
+        }
+
+        // The first pane's size is never used, it gets the rest.
+        return Ok(sizes
+            .iter()
+            .map(|size| size.unwrap_or(Size::Cells(1)))
+            .collect());
+    }
+
+    let total = sizes
+        .iter()
+        .map(|size| match size {
+            Some(Size::Percent(percent)) => *percent as usize,
+            _ => 0,
+        })
+        .sum::<usize>();
+    let unsized_count = sizes.iter().filter(|size| size.is_none()).count();
+    let share = match unsized_count {
+        0 => 0,
+        count => (100 - total) / count,
+    };
+
+    Ok(sizes
+        .iter()
+        .map(|size| size.unwrap_or(Size::Percent(share as u8)))
+        .collect())
 }
 
-fn even_vertical(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-    split_even(total_panes, starting_pane, SplitDirection::Bottom)
+/// Sort `panes` by where they ended up, from left to right.
+///
+/// Splitting the same pane over and over puts each new pane right next to
+/// it, pushing the earlier ones further away, so the order panes are
+/// created in isn't the order they're shown in.
+fn left_to_right(backend: &TrackingBackend, mut panes: Vec<Pane>) -> Vec<Pane> {
+    panes.sort_by_key(|pane| backend.rect(&pane.id).map(|rect| rect.left));
+    panes
 }
 
-fn main_vertical(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-    main_splits(total_panes, starting_pane, SplitDirection::Right)
+fn even_horizontal(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+) -> Result<Vec<Pane>> {
+    split_even(backend, total_panes, starting_pane, SplitDirection::Right)
 }
 
-fn main_vertical_flipped(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-    main_splits(total_panes, starting_pane, SplitDirection::Left)
+fn even_vertical(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+) -> Result<Vec<Pane>> {
+    split_even(backend, total_panes, starting_pane, SplitDirection::Bottom)
 }
 
-fn tiled(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-    let total_panes_even = total_panes.0 % 2 == 0;
-    let mut all_panes = vec![];
-    let left_pane = starting_pane;
-    let right_pane = left_pane.split(&SplitDirection::Right, Some("50"), None, false);
+fn main_vertical(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    main_splits(
+        backend,
+        total_panes,
+        starting_pane,
+        SplitDirection::Right,
+        SplitDirection::Bottom,
+        options.main_size,
+    )
+}
+
+fn main_vertical_flipped(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    main_splits(
+        backend,
+        total_panes,
+        starting_pane,
+        SplitDirection::Left,
+        SplitDirection::Bottom,
+        options.main_size,
+    )
+}
 
-    if total_panes.0 == 2 {
-        all_panes.push(left_pane);
-        all_panes.push(right_pane);
-        return Some(all_panes);
+fn main_horizontal(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    main_splits(
+        backend,
+        total_panes,
+        starting_pane,
+        SplitDirection::Bottom,
+        SplitDirection::Right,
+        options.main_size,
+    )
+}
+
+fn main_horizontal_flipped(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    main_splits(
+        backend,
+        total_panes,
+        starting_pane,
+        SplitDirection::Top,
+        SplitDirection::Right,
+        options.main_size,
+    )
+}
+
+/// How many panes go in each row of a `tiled` layout, from top to bottom.
+///
+/// Without `columns` or `rows`, the grid is kept close to square, with a
+/// column added before each row since panes are usually wider than they are
+/// tall. With only one of them, the other is worked out from
+/// `total_panes`, and with both the grid gets extra rows if it's too small
+/// to fit every pane. Either way, panes are spread over the rows so that no
+/// two rows differ by more than one pane, with the fuller rows on top.
+pub fn tiled_rows(total_panes: usize, columns: Option<usize>, rows: Option<usize>) -> Vec<usize> {
+    let total_panes = total_panes.max(1);
+
+    let columns = match (columns, rows) {
+        (Some(columns), _) => columns,
+        (None, Some(rows)) => total_panes.div_ceil(rows),
+        (None, None) => {
+            let (mut columns, mut rows) = (1, 1);
+            while columns * rows < total_panes {
+                columns += 1;
+                if columns * rows < total_panes {
+                    rows += 1;
+                }
+            }
+            columns
+        }
     }
+    .clamp(1, total_panes);
+    let rows = rows
+        .unwrap_or(0)
+        .max(total_panes.div_ceil(columns))
+        .min(total_panes);
+
+    spread(total_panes, rows)
+}
 
-    let per_side;
-    if total_panes_even {
-        per_side = TotalPanes(total_panes.0 / 2);
-    } else {
-        per_side = TotalPanes((total_panes.0 - 1) / 2);
+/// Spread `total_panes` over `groups` so that no two groups differ by more
+/// than one pane, with the fuller groups first.
+fn spread(total_panes: usize, groups: usize) -> Vec<usize> {
+    let (per_group, fuller_groups) = (total_panes / groups, total_panes % groups);
+    (0..groups)
+        .map(|group| per_group + usize::from(group < fuller_groups))
+        .collect()
+}
 
-        // If panes are odd, create a bottom pane at the top level
-        let bottom_pane = left_pane.split(&SplitDirection::Bottom, None, None, true);
-        all_panes.push(bottom_pane);
+/// Splits dividing a pane along `direction` into one group per entry in
+/// `counts`, each divided the other way between that many panes. `sizes`
+/// sizes the groups, from the first one on.
+fn grid(direction: SplitDirection, counts: Vec<usize>, sizes: &[Size]) -> SplitTree {
+    let across = match direction {
+        SplitDirection::Right | SplitDirection::Left => SplitDirection::Bottom,
+        SplitDirection::Bottom | SplitDirection::Top => SplitDirection::Right,
+    };
+
+    SplitTree::Split {
+        size: None,
+        direction,
+        children: counts
+            .into_iter()
+            .enumerate()
+            .map(|(index, count)| SplitTree::Split {
+                size: sizes.get(index).copied(),
+                direction: across,
+                children: vec![SplitTree::Pane { size: None }; count],
+            })
+            .collect(),
     }
+}
 
-    let mut left_panes = even_vertical(per_side, left_pane.clone()).unwrap_or(vec![]);
-    let mut right_panes = even_vertical(per_side, right_pane.clone()).unwrap_or(vec![]);
-    all_panes.append(&mut left_panes);
-    all_panes.append(&mut right_panes);
-    Some(all_panes)
+fn tiled(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    let rows = tiled_rows(total_panes.0, options.columns, options.rows);
+
+    grid(SplitDirection::Bottom, rows, &[]).create(backend, starting_pane)
 }
 
-fn three_columns(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
-    let mut cols =
-        split_even(TotalPanes(3), starting_pane.clone(), SplitDirection::Right).unwrap_or(vec![]);
+/// `count` columns, with the fuller ones on the left when the panes don't
+/// divide evenly between them. There's always a pane in every column.
+fn columns(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    count: usize,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    let columns = spread(total_panes.0.max(count), count);
+    let sizes = options.column_sizes.as_deref().unwrap_or_default();
 
-    // HACK: Wezterm's split rules are a little finnicky.
-    // When generating the columns, the last column gets put
-    // in the center spot in the tab instead of at the end, which is what
-    // would be expected from 3 horizontal splits.
-    // To combat this, we manually move the last tab back one in the vector.
+    grid(SplitDirection::Right, columns, sizes).create(backend, starting_pane)
+}
 
-    // The columns should exist. It's safe to panic otherwise.
-    let last_col = cols.pop().unwrap();
-    let middle_col = cols.pop().unwrap();
-    cols.push(last_col);
-    cols.push(middle_col);
+/// `count` rows, with the fuller ones on top when the panes don't divide
+/// evenly between them. There's always a pane in every row.
+fn rows(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    count: usize,
+) -> Result<Vec<Pane>> {
+    let rows = spread(total_panes.0.max(count), count);
+
+    grid(SplitDirection::Bottom, rows, &[]).create(backend, starting_pane)
+}
+
+/// Split each new pane in half, turning right, down, left and up in turn
+/// so the panes spiral inwards.
+fn spiral(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+) -> Result<Vec<Pane>> {
+    let turns = [
+        SplitDirection::Right,
+        SplitDirection::Bottom,
+        SplitDirection::Left,
+        SplitDirection::Top,
+    ];
+    let mut panes = vec![starting_pane];
+
+    for direction in turns.into_iter().cycle().take(total_panes.0 - 1) {
+        let last = panes.last().unwrap().clone();
+        let halves = split_even(backend, TotalPanes(2), last, direction)?;
+        panes.extend(halves.into_iter().skip(1));
+    }
+
+    Ok(panes)
+}
+
+/// A main column between two stacks, which take the other panes between
+/// them with the left one getting any extra pane. Without `column_sizes`,
+/// the main column is `main_size` wide, or half the tab, and the stacks
+/// share the rest.
+fn centered_main(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    let sizes = match &options.column_sizes {
+        Some(sizes) => sizes.iter().copied().map(Some).collect(),
+        None => {
+            let main_size = options.main_size.unwrap_or(Size::Percent(50));
+            Layout::CenteredMain.main_column_sizes(main_size).unwrap()
+        }
+    };
+    let cols = split_sized(backend, starting_pane, SplitDirection::Right, &sizes)?;
+
+    let stacked = (total_panes.0 - 1).max(2);
+    let left = stacked.div_ceil(2);
+
+    let mut panes = vec![cols[1].clone()];
+    for (col, count) in [(&cols[0], left), (&cols[2], stacked - left)] {
+        panes.append(&mut split_even(
+            backend,
+            TotalPanes(count),
+            col.clone(),
+            SplitDirection::Bottom,
+        )?);
+    }
+
+    Ok(panes)
+}
+
+fn three_columns(
+    backend: &TrackingBackend,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
+    let cols = match &options.column_sizes {
+        Some(sizes) => split_sized(
+            backend,
+            starting_pane,
+            SplitDirection::Right,
+            &sizes.iter().copied().map(Some).collect::<Vec<_>>(),
+        )?,
+        None => split_even(
+            backend,
+            TotalPanes(3),
+            starting_pane.clone(),
+            SplitDirection::Right,
+        )?,
+    };
+    let cols = left_to_right(backend, cols);
 
     // If we get lte 3 panes as input, return the colunms.
     // This can happen if a user wants < 3 commands to be run,
     // but still have a third column ready for other manual activity.
     if total_panes.0 <= 3 {
-        return Some(cols);
+        return Ok(cols);
     }
 
     let num_cols = cols.len();
@@ -189,41 +1084,61 @@ fn three_columns(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pan
             break;
         }
 
-        let v_panes = split_even(
-            TotalPanes(panes_per_col as usize + 1),
+        // The last column only gets whatever is left over, which
+        // can be less than a full column's worth of rows.
+        let rows_in_col = (panes_per_col as usize).min(panes_left);
+        let mut v_panes = split_even(
+            backend,
+            TotalPanes(rows_in_col + 1),
             pane.clone(),
             SplitDirection::Bottom,
-        );
+        )?;
 
-        panes_left -= panes_per_col as usize;
+        panes_left -= rows_in_col;
 
-        if let Some(mut created_panes) = v_panes {
-            panes.append(&mut created_panes);
-        }
+        panes.append(&mut v_panes);
     }
 
-    Some(panes)
+    Ok(panes)
 }
 
-fn double_main_vertical(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
+fn double_main_vertical(
+    backend: &TrackingBackend,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
     let num_cols = 3;
-    let cols = split_even(
-        TotalPanes(num_cols),
-        starting_pane.clone(),
-        SplitDirection::Right,
-    )
-    .unwrap_or(vec![]);
-
-    // HACK: Wezterm's split rules are a little finnicky.
-    // When generating the columns, the last column gets put
-    // in the center spot in the tab instead of at the end, which is what
-    // would be expected from 3 horizontal splits.
-    // To combat this, we manually move the last tab back one in the vector.
+    let cols = match (options.main_size, &options.column_sizes) {
+        (_, Some(sizes)) => split_sized(
+            backend,
+            starting_pane,
+            SplitDirection::Right,
+            &sizes.iter().copied().map(Some).collect::<Vec<_>>(),
+        )?,
+        // Peel the main columns off the left so the last column is the one
+        // that keeps whatever they leave.
+        (Some(main_size), None) => split_sized(
+            backend,
+            starting_pane,
+            SplitDirection::Left,
+            &Layout::DoubleMainVertical
+                .main_column_sizes(main_size)
+                .unwrap(),
+        )?,
+        (None, None) => split_even(
+            backend,
+            TotalPanes(num_cols),
+            starting_pane.clone(),
+            SplitDirection::Right,
+        )?,
+    };
+    let cols = left_to_right(backend, cols);
 
     // The columns should exist. It's safe to panic otherwise.
-    let visually_first_col = cols.get(0).unwrap();
-    let visually_middle_col = cols.get(2).unwrap();
-    let visually_last_col = cols.get(1).unwrap();
+    let visually_first_col = cols.first().unwrap();
+    let visually_middle_col = cols.get(1).unwrap();
+    let visually_last_col = cols.get(2).unwrap();
 
     // visually_last_col will be included in `v_panes`, so we don't include
     // it in the initialization of `panes`.
@@ -233,63 +1148,58 @@ fn double_main_vertical(total_panes: TotalPanes, starting_pane: Pane) -> Option<
     let total_panes_to_gen = total_panes.0 - panes.len();
 
     if total_panes_to_gen == 0 {
-        return Some(panes);
+        return Ok(panes);
     }
 
-    let v_panes = split_even(
+    let mut v_panes = split_even(
+        backend,
         TotalPanes(total_panes_to_gen),
         visually_last_col.clone(),
         SplitDirection::Bottom,
-    );
+    )?;
 
-    if let Some(mut created_panes) = v_panes {
-        panes.append(&mut created_panes);
-    }
+    panes.append(&mut v_panes);
 
-    Some(panes)
+    Ok(panes)
 }
 
-fn double_main_horizontal(total_panes: TotalPanes, starting_pane: Pane) -> Option<Vec<Pane>> {
+fn double_main_horizontal(
+    backend: &dyn Multiplexer,
+    total_panes: TotalPanes,
+    starting_pane: Pane,
+    options: &LayoutOptions,
+) -> Result<Vec<Pane>> {
     let row_count = 2;
-    let rows = split_even(
-        TotalPanes(row_count),
-        starting_pane.clone(),
-        SplitDirection::Bottom,
-    )
-    .unwrap_or(vec![]);
-
-    // The columns should exist. It's safe to panic otherwise.
-    let visually_first_row = rows.get(0).unwrap();
-    let visually_last_row = rows.get(1).unwrap();
-
-    let mut panes = vec![];
+    // The main panes share the bottom row, which is the new pane.
+    let main_size = options.main_size.unwrap_or(Size::Percent(50));
+    let visually_first_row = &starting_pane;
+    let visually_last_row = &starting_pane.split(
+        backend,
+        &SplitDirection::Bottom,
+        Some(main_size),
+        None,
+        false,
+    )?;
 
-    let bottom_panes = split_even(
+    let mut panes = split_even(
+        backend,
         TotalPanes(2),
         visually_last_row.clone(),
         SplitDirection::Right,
-    );
+    )?;
 
-    if let Some(mut created_panes) = bottom_panes {
-        panes.append(&mut created_panes);
-    };
-
-    // Remove row panes from count since they've already been generated.
-    let total_panes_to_gen = total_panes.0 - row_count;
+    // Remove the bottom row's panes from the count since they've already
+    // been generated. The top row is always there, even when it's empty.
+    let top_row_count = (total_panes.0 - row_count).max(1);
 
-    if total_panes_to_gen == 0 {
-        return Some(panes);
-    }
-
-    let top_panes = split_even(
-        TotalPanes(total_panes_to_gen),
+    let mut top_panes = split_even(
+        backend,
+        TotalPanes(top_row_count),
         visually_first_row.clone(),
         SplitDirection::Right,
-    );
+    )?;
 
-    if let Some(mut created_panes) = top_panes {
-        panes.append(&mut created_panes);
-    }
+    panes.append(&mut top_panes);
 
-    Some(panes)
+    Ok(panes)
 }
@@ -0,0 +1,387 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::wezterm::pane::{Size, SplitDirection};
+
+use super::SplitTree;
+
+/// A layout drawn with ASCII boxes, the way the README draws them:
+///
+/// ```text
+/// .------------------.------------------.
+/// | (0)              | (1)              |
+/// |                  |                  |
+/// |                  |------------------|
+/// |                  | (2)              |
+/// '------------------'------------------'
+/// ```
+///
+/// Every line has to go all the way across the box it's in, so the cells
+/// can be made by splitting, and every cell is numbered with the entry in
+/// `panes` it shows. Sizes are proportional to how many characters wide or
+/// tall each cell is.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Diagram {
+    pub tree: SplitTree,
+    /// The number of each of the tree's panes, in the order they're created.
+    pub labels: Vec<usize>,
+}
+
+/// Returned when a diagram can't be turned into splits. `line` and `column`
+/// are 1-based and relative to the diagram itself.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ParseDiagramError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseDiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} of the diagram: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Error for ParseDiagramError {}
+
+impl FromStr for Diagram {
+    type Err = ParseDiagramError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let grid = Grid {
+            rows: source.lines().map(|line| line.chars().collect()).collect(),
+        };
+        let outline = grid.outline()?;
+
+        let mut labels = vec![];
+        let tree = grid.parse(outline, &mut labels)?;
+
+        let mut seen = vec![false; labels.len()];
+        for (label, (row, column)) in &labels {
+            match seen.get_mut(*label) {
+                Some(false) => seen[*label] = true,
+                Some(true) => {
+                    return Err(error_at(
+                        *row,
+                        *column,
+                        format!("({}) is used for more than one cell", label),
+                    ))
+                }
+                None => {
+                    return Err(error_at(
+                        *row,
+                        *column,
+                        format!(
+                        "({}) is out of range, the {} cells have to be numbered from (0) to ({})",
+                        label,
+                        labels.len(),
+                        labels.len() - 1
+                    ),
+                    ))
+                }
+            }
+        }
+
+        Ok(Diagram {
+            tree,
+            labels: labels.into_iter().map(|(label, _)| label).collect(),
+        })
+    }
+}
+
+/// The borders of a box in the diagram, as 0-based rows and columns.
+#[derive(Clone, Copy)]
+struct Rect {
+    top: usize,
+    bottom: usize,
+    left: usize,
+    right: usize,
+}
+
+struct Grid {
+    rows: Vec<Vec<char>>,
+}
+
+impl Grid {
+    fn get(&self, row: usize, column: usize) -> char {
+        self.rows
+            .get(row)
+            .and_then(|r| r.get(column))
+            .copied()
+            .unwrap_or(' ')
+    }
+
+    /// The box around the whole diagram.
+    fn outline(&self) -> Result<Rect, ParseDiagramError> {
+        let is_blank = |row: &Vec<char>| row.iter().all(|c| c.is_whitespace());
+        let top = self.rows.iter().position(|row| !is_blank(row));
+        let bottom = self.rows.iter().rposition(|row| !is_blank(row));
+        let (Some(top), Some(bottom)) = (top, bottom) else {
+            return Err(error_at(0, 0, String::from("the diagram is empty")));
+        };
+
+        let left = self.rows[top]
+            .iter()
+            .position(|c| !c.is_whitespace())
+            .unwrap();
+        let right = self.rows[top]
+            .iter()
+            .rposition(|c| !c.is_whitespace())
+            .unwrap();
+        let outline = Rect {
+            top,
+            bottom,
+            left,
+            right,
+        };
+
+        if bottom - top < 2 || right - left < 2 {
+            return Err(error_at(
+                top,
+                left,
+                String::from("the diagram has to be a box with room for at least one cell"),
+            ));
+        }
+        for column in left..=right {
+            for row in [top, bottom] {
+                if !is_line(self.get(row, column)) {
+                    return Err(self.unclosed(row, column));
+                }
+            }
+        }
+        for row in top..=bottom {
+            for column in [left, right] {
+                if !is_line(self.get(row, column)) {
+                    return Err(self.unclosed(row, column));
+                }
+            }
+        }
+
+        Ok(outline)
+    }
+
+    fn unclosed(&self, row: usize, column: usize) -> ParseDiagramError {
+        error_at(
+            row,
+            column,
+            String::from("the outline of the diagram isn't a closed box"),
+        )
+    }
+
+    /// Turn the inside of `rect` into a tree, pushing the label and position
+    /// of every cell onto `labels` as they're found.
+    fn parse(
+        &self,
+        rect: Rect,
+        labels: &mut Vec<(usize, (usize, usize))>,
+    ) -> Result<SplitTree, ParseDiagramError> {
+        let rows = rect.top + 1..rect.bottom;
+        let columns = rect.left + 1..rect.right;
+
+        // Columns are cut first, so a grid of cells is read column by column.
+        let vertical_cuts = columns
+            .clone()
+            .filter(|&column| self.is_cut(rows.clone().map(|row| (row, column)), '|'))
+            .collect::<Vec<_>>();
+        if !vertical_cuts.is_empty() {
+            let children = edges(rect.left, &vertical_cuts, rect.right)
+                .map(|(left, right)| Rect {
+                    left,
+                    right,
+                    ..rect
+                })
+                .collect::<Vec<_>>();
+            return self.split(SplitDirection::Right, children, labels);
+        }
+
+        let horizontal_cuts = rows
+            .clone()
+            .filter(|&row| self.is_cut(columns.clone().map(|column| (row, column)), '-'))
+            .collect::<Vec<_>>();
+        if !horizontal_cuts.is_empty() {
+            let children = edges(rect.top, &horizontal_cuts, rect.bottom)
+                .map(|(top, bottom)| Rect {
+                    top,
+                    bottom,
+                    ..rect
+                })
+                .collect::<Vec<_>>();
+            return self.split(SplitDirection::Bottom, children, labels);
+        }
+
+        self.cell(rect, labels)
+    }
+
+    /// Whether every position is part of a line, at least one of them
+    /// being `wall`, the character that line is drawn with.
+    fn is_cut(&self, mut positions: impl Iterator<Item = (usize, usize)>, wall: char) -> bool {
+        let mut has_wall = false;
+        let all_lines = positions.all(|(row, column)| {
+            let c = self.get(row, column);
+            has_wall |= c == wall;
+            is_line(c)
+        });
+
+        all_lines && has_wall
+    }
+
+    fn split(
+        &self,
+        direction: SplitDirection,
+        children: Vec<Rect>,
+        labels: &mut Vec<(usize, (usize, usize))>,
+    ) -> Result<SplitTree, ParseDiagramError> {
+        let lengths = children
+            .iter()
+            .map(|rect| match direction {
+                SplitDirection::Right | SplitDirection::Left => rect.right - rect.left - 1,
+                SplitDirection::Bottom | SplitDirection::Top => rect.bottom - rect.top - 1,
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(index) = lengths.iter().position(|length| *length == 0) {
+            let rect = children[index];
+            return Err(error_at(
+                rect.top,
+                rect.left,
+                String::from("two lines are right next to each other, with no cell between them"),
+            ));
+        }
+
+        // Round every size down and let the first child take what's left,
+        // since it gets whatever the others don't anyway.
+        let total = lengths.iter().sum::<usize>();
+        let mut percentages = lengths
+            .iter()
+            .map(|length| (length * 100 / total).max(1))
+            .collect::<Vec<_>>();
+        percentages[0] = match 100usize.checked_sub(percentages[1..].iter().sum()) {
+            Some(rest) if rest > 0 => rest,
+            _ => {
+                return Err(error_at(
+                    children[0].top,
+                    children[0].left,
+                    String::from("there are too many cells side by side to size them"),
+                ))
+            }
+        };
+
+        let children = children
+            .into_iter()
+            .zip(percentages)
+            .map(|(rect, percent)| {
+                let size = Some(Size::Percent(percent as u8));
+                Ok(match self.parse(rect, labels)? {
+                    SplitTree::Pane { .. } => SplitTree::Pane { size },
+                    SplitTree::Split {
+                        direction,
+                        children,
+                        ..
+                    } => SplitTree::Split {
+                        size,
+                        direction,
+                        children,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SplitTree::Split {
+            size: None,
+            direction,
+            children,
+        })
+    }
+
+    /// A cell that isn't split any further. It has to have a `(n)` label,
+    /// and anything that looks like a line in it is one that doesn't go all
+    /// the way across.
+    fn cell(
+        &self,
+        rect: Rect,
+        labels: &mut Vec<(usize, (usize, usize))>,
+    ) -> Result<SplitTree, ParseDiagramError> {
+        let mut label = None;
+
+        for row in rect.top + 1..rect.bottom {
+            let text = (rect.left + 1..rect.right)
+                .map(|column| self.get(row, column))
+                .collect::<String>();
+
+            if let Some(offset) = text.find(['|', '+']).or_else(|| text.find("--")) {
+                return Err(error_at(
+                    row,
+                    rect.left + 1 + text[..offset].chars().count(),
+                    String::from("this line doesn't go all the way across the cell it's in, so the cell can't be made by splitting"),
+                ));
+            }
+
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find('(') {
+                let after = &rest[start + 1..];
+                let digits =
+                    after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                let column =
+                    rect.left + 1 + text[..text.len() - rest.len() + start].chars().count();
+                rest = after;
+
+                if digits == 0 || !after[digits..].starts_with(')') {
+                    continue;
+                }
+                if label.is_some() {
+                    return Err(error_at(
+                        row,
+                        column,
+                        String::from("a cell can only have one number"),
+                    ));
+                }
+                let Ok(number) = after[..digits].parse() else {
+                    return Err(error_at(
+                        row,
+                        column,
+                        String::from("this number is too big"),
+                    ));
+                };
+                label = Some((number, (row, column)));
+            }
+        }
+
+        match label {
+            Some((label, position)) => {
+                labels.push((label, position));
+                Ok(SplitTree::Pane { size: None })
+            }
+            None => Err(error_at(
+                rect.top + 1,
+                rect.left + 1,
+                String::from("this cell doesn't have a number like (0) for the pane it shows"),
+            )),
+        }
+    }
+}
+
+fn is_line(c: char) -> bool {
+    matches!(c, '|' | '-' | '+' | '.' | '\'')
+}
+
+/// Consecutive pairs of edges from `start`, through every cut, to `end`.
+fn edges(start: usize, cuts: &[usize], end: usize) -> impl Iterator<Item = (usize, usize)> {
+    let edges = std::iter::once(start)
+        .chain(cuts.iter().copied())
+        .chain(std::iter::once(end))
+        .collect::<Vec<_>>();
+
+    (0..edges.len() - 1).map(move |i| (edges[i], edges[i + 1]))
+}
+
+/// An error at the 0-based `row` and `column` of the grid.
+fn error_at(row: usize, column: usize, message: String) -> ParseDiagramError {
+    ParseDiagramError {
+        line: row + 1,
+        column: column + 1,
+        message,
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{error::Result, multiplexer::Multiplexer, wezterm::pane::Pane};
+
+use super::{Layout, LayoutOptions, LayoutOptionsError, ParseLayoutError, TotalPanes};
+
+/// A way of splitting a window into panes. Every [`Layout`] is one, and
+/// more can be added under names of their own with a [`LayoutRegistry`].
+pub trait LayoutStrategy: Send + Sync {
+    /// Split `starting_pane` into `total_panes` panes, or more if the
+    /// layout always creates more, and return them in the order a window's
+    /// `panes` fill them in, starting with `starting_pane`'s place.
+    fn create(
+        &self,
+        backend: &dyn Multiplexer,
+        total_panes: TotalPanes,
+        starting_pane: Pane,
+        options: &LayoutOptions,
+    ) -> Result<Vec<Pane>>;
+
+    /// The fewest panes the layout creates.
+    fn min_panes(&self) -> usize {
+        1
+    }
+
+    /// Check that `options` can be applied to the layout. By default, a
+    /// layout doesn't take any.
+    fn check_options(
+        &self,
+        options: &LayoutOptions,
+    ) -> std::result::Result<(), LayoutOptionsError> {
+        match options.first_set() {
+            Some(option) => Err(LayoutOptionsError {
+                option: String::from(option),
+                message: format!("the layout doesn't take `{}`", option),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl LayoutStrategy for Layout {
+    fn create(
+        &self,
+        backend: &dyn Multiplexer,
+        total_panes: TotalPanes,
+        starting_pane: Pane,
+        options: &LayoutOptions,
+    ) -> Result<Vec<Pane>> {
+        Layout::create(self, backend, total_panes, starting_pane, options)
+    }
+
+    fn min_panes(&self) -> usize {
+        Layout::min_panes(self)
+    }
+
+    fn check_options(
+        &self,
+        options: &LayoutOptions,
+    ) -> std::result::Result<(), LayoutOptionsError> {
+        Layout::check_options(self, options)
+    }
+}
+
+/// Layouts a window's `layout` can name on top of the built-in ones.
+///
+/// ```
+/// use weztermocil::{
+///     config::{WindowConfig, YAMLConfig},
+///     layout::{Layout, LayoutRegistry},
+///     Session,
+/// };
+///
+/// // Two columns, whatever the number of panes.
+/// let layouts = LayoutRegistry::new().register("pair", Layout::Columns(2));
+/// let config = YAMLConfig::new().window(WindowConfig::new().layout("pair").pane("vim"));
+///
+/// let session = Session::new(config).layouts(layouts);
+/// ```
+#[derive(Clone, Default)]
+pub struct LayoutRegistry {
+    strategies: HashMap<String, Arc<dyn LayoutStrategy>>,
+}
+
+impl LayoutRegistry {
+    pub fn new() -> LayoutRegistry {
+        LayoutRegistry::default()
+    }
+
+    /// Make `strategy` available as `name`. A strategy registered under
+    /// the name of a built-in layout is used instead of it.
+    pub fn register(
+        mut self,
+        name: &str,
+        strategy: impl LayoutStrategy + 'static,
+    ) -> LayoutRegistry {
+        self.strategies
+            .insert(String::from(name), Arc::new(strategy));
+        self
+    }
+
+    /// The names of every registered strategy, in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self
+            .strategies
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// The layout called `name`, either a registered strategy or one of
+    /// [`Layout::NAMES`].
+    pub fn resolve(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Arc<dyn LayoutStrategy>, ParseLayoutError> {
+        match self.strategies.get(name) {
+            Some(strategy) => Ok(Arc::clone(strategy)),
+            None => Ok(Arc::new(name.parse::<Layout>()?)),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    error::Result,
+    multiplexer::Multiplexer,
+    wezterm::pane::{Pane, Size, SplitDirection},
+};
+
+use super::{check_sizes, split_sized, LayoutOptionsError};
+
+/// A layout described as nested splits, used by [`super::Layout::Custom`].
+///
+/// Panes are created in the order their leaves appear in the tree, reading
+/// depth first, so a tree written left to right and top to bottom gives
+/// panes in the order they're seen on screen.
+#[derive(PartialEq, Clone, Debug)]
+pub enum SplitTree {
+    /// A single pane.
+    Pane { size: Option<Size> },
+    /// A pane divided between `children`, from left to right for
+    /// [`SplitDirection::Right`] or top to bottom for [`SplitDirection::Bottom`].
+    Split {
+        size: Option<Size>,
+        direction: SplitDirection,
+        children: Vec<SplitTree>,
+    },
+}
+
+impl SplitTree {
+    /// The node's size within the split it's part of. Nodes without one
+    /// share whatever space their sized siblings leave.
+    pub fn size(&self) -> Option<Size> {
+        match self {
+            SplitTree::Pane { size } | SplitTree::Split { size, .. } => *size,
+        }
+    }
+
+    /// How many panes the tree creates.
+    pub fn pane_count(&self) -> usize {
+        match self {
+            SplitTree::Pane { .. } => 1,
+            SplitTree::Split { children, .. } => children.iter().map(SplitTree::pane_count).sum(),
+        }
+    }
+
+    /// Check every split in the tree can be carried out. `path` is where
+    /// the tree is in the layout file, e.g. `tree`.
+    pub fn check(&self, path: &str) -> std::result::Result<(), LayoutOptionsError> {
+        let SplitTree::Split { children, .. } = self else {
+            return Ok(());
+        };
+
+        if children.is_empty() {
+            return Err(LayoutOptionsError {
+                option: format!("{}.children", path),
+                message: String::from("a split needs at least one child"),
+            });
+        }
+        check_sizes(&child_sizes(children)).map_err(|message| LayoutOptionsError {
+            option: format!("{}.children", path),
+            message,
+        })?;
+
+        for (index, child) in children.iter().enumerate() {
+            child.check(&format!("{}.children[{}]", path, index))?;
+        }
+
+        Ok(())
+    }
+
+    /// Split `starting_pane` according to the tree and return the panes in
+    /// leaf order.
+    pub fn create(&self, backend: &dyn Multiplexer, starting_pane: Pane) -> Result<Vec<Pane>> {
+        let SplitTree::Split {
+            direction,
+            children,
+            ..
+        } = self
+        else {
+            return Ok(vec![starting_pane]);
+        };
+
+        let child_panes = split_sized(backend, starting_pane, *direction, &child_sizes(children))?;
+
+        let mut panes = vec![];
+        for (child, pane) in children.iter().zip(child_panes) {
+            panes.append(&mut child.create(backend, pane)?);
+        }
+
+        Ok(panes)
+    }
+}
+
+fn child_sizes(children: &[SplitTree]) -> Vec<Option<Size>> {
+    children.iter().map(SplitTree::size).collect()
+}
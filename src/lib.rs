@@ -1,5 +1,14 @@
 static WEZTERM_CLI: &str = "wezterm";
 
+pub mod config;
+pub mod diagnostics;
+pub mod error;
 pub mod format;
+pub mod geometry;
 pub mod layout;
+pub mod multiplexer;
+pub mod preview;
+pub mod session;
 pub mod wezterm;
+
+pub use session::{Session, SessionEvent};
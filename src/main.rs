@@ -1,48 +1,94 @@
 use std::{
     env, fs,
     ops::Deref,
+    path::Path,
     process::{self, Command},
 };
 
 use clap::Parser;
-use serde::{Deserialize, Serialize};
 use shellexpand::tilde;
 use weztermocil::{
-    layout::{Layout, TotalPanes},
-    wezterm::pane::Pane,
+    config,
+    diagnostics::{Diagnostic, Severity},
+    error::{self, WeztermocilError},
+    format::shell_quote,
+    layout::Layout,
+    multiplexer::Multiplexer,
+    preview,
+    wezterm::{pane::Pane, DryRunBackend, WeztermCliBackend},
+    Session, SessionEvent,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct PaneConfigOptions {
-    commands: Option<Vec<String>>,
-    #[serde(default)]
-    focus: bool,
+/// Print diagnostics for every layout in `layouts`, failing if any of
+/// them has errors.
+fn validate_layouts(layouts: &[String]) -> error::Result<()> {
+    let mut invalid = 0;
+
+    for layout in layouts {
+        let path = find_layout(layout);
+
+        let diagnostics = match &path {
+            Ok(path) => config::validate_file(path),
+            Err(error) => vec![Diagnostic::error("", error.clone())],
+        };
+        let file = path.as_deref().unwrap_or(layout);
+
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic.render(file));
+        }
+
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            invalid += 1;
+        } else if diagnostics.is_empty() {
+            println!("{}: ok", file);
+        }
+    }
+
+    match invalid {
+        0 => Ok(()),
+        _ => Err(WeztermocilError::Config(format!(
+            "{} of {} layout(s) have errors",
+            invalid,
+            layouts.len()
+        ))),
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(untagged)]
-enum PaneConfig {
-    Commands(Vec<String>),
-    Hash(Vec<PaneConfigOptions>),
+/// Show what the session did or would have done, the way `--dry-run` prints
+/// the `wezterm cli` calls.
+fn print_event(event: &SessionEvent) {
+    match event {
+        SessionEvent::WouldRunPre { command } => println!("sh -c {}", shell_quote(command)),
+        SessionEvent::WouldCreateRoot { dir } => println!("mkdir -p {}", shell_quote(dir)),
+        SessionEvent::RollingBack { panes } => {
+            eprintln!("Closing the {} pane(s) created before the failure", panes)
+        }
+        SessionEvent::RollbackFailed { error } => {
+            eprintln!("Failed to close every pane: {}", error)
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct WindowConfig {
-    name: Option<String>,
-    root: Option<String>,
-    layout: Option<String>,
-    panes: Option<PaneConfig>,
-    command: Option<String>,
-    commands: Option<Vec<String>>,
-    #[serde(default)]
-    focus: bool,
+/// Print every window of the layout as a box diagram, laid out on a virtual
+/// tab instead of in wezterm.
+fn preview_layout(layout: &str) -> error::Result<()> {
+    let path = find_layout(layout).map_err(WeztermocilError::Config)?;
+    let config = config::YAMLConfig::from_file(&path)?;
+
+    print!(
+        "{}",
+        preview::render(&config, preview::COLUMNS, preview::ROWS)?
+    );
+
+    Ok(())
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct YAMLConfig {
-    name: Option<String>,
-    windows: Option<Vec<WindowConfig>>,
-    pre: Option<String>,
+/// Find a layout given either as a path or by name.
+fn find_layout(layout: &str) -> Result<String, String> {
+    match Path::new(layout).is_file() {
+        true => Ok(String::from(layout)),
+        false => get_path_for_layout_file(&qualify_layout_file(layout)),
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -59,29 +105,25 @@ struct Args {
     show: Option<String>,
     #[arg(long, action)]
     list: bool,
+    /// List the layouts windows can use, with a line on each
+    #[arg(long, action)]
+    list_layouts: bool,
+    /// Check layout files for mistakes without running them
+    #[arg(long, num_args = 1.., value_name = "LAYOUT")]
+    validate: Vec<String>,
+    /// Draw the layout's windows as box diagrams instead of running it
+    #[arg(long, value_name = "LAYOUT")]
+    preview: Option<String>,
+    /// Print the wezterm cli calls the layout would make instead of running them
+    #[arg(long, action)]
+    dry_run: bool,
+    /// Leave panes that were already created open if launching the layout fails
+    #[arg(long, action)]
+    no_rollback: bool,
 }
 
 const DIRS: &[&str] = &[".weztermocil", ".teamocil", ".itermocil"];
 
-type PaneIndex = usize;
-type WindowIndex = usize;
-struct FocusTuple(WindowIndex, PaneIndex);
-struct WindowPanes(Vec<Vec<Pane>>);
-
-fn layout_string_to_enum(name: &str) -> Layout {
-    match name {
-        "tiled" => Layout::Tiled,
-        "even-horizontal" => Layout::EvenHorizontal,
-        "main-vertical" => Layout::MainVertical,
-        "main-vertical-flipped" => Layout::MainVerticalFlipped,
-        "even-vertical" => Layout::EvenVertical,
-        "3_columns" => Layout::ThreeColumns,
-        "double-main-horizontal" => Layout::DoubleMainHorizontal,
-        "double-main-vertical" => Layout::DoubleMainVertical,
-        &_ => Layout::Tiled,
-    }
-}
-
 fn get_global_config_path() -> Result<String, String> {
     let mut path = String::from("");
     for dir in DIRS {
@@ -96,7 +138,7 @@ fn get_global_config_path() -> Result<String, String> {
         };
     }
 
-    if path == "" {
+    if path.is_empty() {
         return Err(String::from("Couldn't find a .weztermocil, .teamocil or .itermocil folder in the home directory (~)\nPlease make sure one of them exists before continuing"));
     }
 
@@ -115,7 +157,7 @@ fn get_local_config_path() -> Result<String, String> {
         };
     }
 
-    if path == "" {
+    if path.is_empty() {
         return Err(String::from("Couldn't find a .weztermocil, .teamocil or .itermocil folder in the current directory\nPlease make sure one of them exists before continuing"));
     }
 
@@ -142,20 +184,34 @@ fn list_layouts() {
         }
     }
 
-    match local {
-        Ok(p) => {
-            // We've already validated that the path exists, so we can unwrap here.
-            let entries = fs::read_dir(p).unwrap().collect::<Vec<_>>();
-            if entries.len() < 1 {
-                return;
-            }
+    if let Ok(p) = local {
+        // We've already validated that the path exists, so we can unwrap here.
+        let entries = fs::read_dir(p).unwrap().collect::<Vec<_>>();
+        if entries.is_empty() {
+            return;
+        }
 
-            println!("\nLocal layouts:");
-            for entry in entries {
-                println!("=> {}", entry.unwrap().file_name().into_string().unwrap());
-            }
+        println!("\nLocal layouts:");
+        for entry in entries {
+            println!("=> {}", entry.unwrap().file_name().into_string().unwrap());
         }
-        Err(_) => {}
+    }
+}
+
+fn list_builtin_layouts() {
+    let width = Layout::NAMES
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0);
+
+    for name in Layout::NAMES {
+        println!(
+            "{:width$}  {}",
+            name,
+            Layout::describe(name).unwrap_or_default(),
+            width = width
+        );
     }
 }
 
@@ -168,37 +224,31 @@ fn get_path_for_layout_file(layout_name: &str) -> Result<String, String> {
     let current_dir_fp = format!("{}/{}", current_dir, layout_name);
     let in_current_dir = fs::File::open(&current_dir_fp);
 
-    if let Ok(_) = in_current_dir {
+    if in_current_dir.is_ok() {
         return Ok(current_dir_fp);
     }
 
     let local_layout_dir_path = get_local_config_path();
-    match local_layout_dir_path {
-        Ok(p) => {
-            let local_layout_dir_fp = format!("{}/{}/{}", current_dir, p, layout_name);
-            let in_local_layout_dir = fs::File::open(&local_layout_dir_fp);
+    if let Ok(p) = local_layout_dir_path {
+        let local_layout_dir_fp = format!("{}/{}/{}", current_dir, p, layout_name);
+        let in_local_layout_dir = fs::File::open(&local_layout_dir_fp);
 
-            if let Ok(_) = in_local_layout_dir {
-                return Ok(local_layout_dir_fp);
-            }
+        if in_local_layout_dir.is_ok() {
+            return Ok(local_layout_dir_fp);
         }
-        Err(_) => {}
     }
 
     let global_path = get_global_config_path();
-    match global_path {
-        Ok(p) => {
-            let global_layout_fp = format!("{}/{}", p, layout_name);
-            let in_global_layout = fs::File::open(&global_layout_fp);
+    if let Ok(p) = global_path {
+        let global_layout_fp = format!("{}/{}", p, layout_name);
+        let in_global_layout = fs::File::open(&global_layout_fp);
 
-            if let Ok(_) = in_global_layout {
-                return Ok(global_layout_fp);
-            }
+        if in_global_layout.is_ok() {
+            return Ok(global_layout_fp);
         }
-        Err(_) => {}
     }
 
-    return Err(String::from("Couldn't find layout"));
+    Err(String::from("Couldn't find layout"))
 }
 
 fn show_layout_contents(path: String) {
@@ -206,7 +256,7 @@ fn show_layout_contents(path: String) {
     println!("{}", contents);
 }
 
-fn edit_layout(path: String) -> () {
+fn edit_layout(path: String) {
     let editor = env::var("EDITOR").unwrap();
     Command::new(editor)
         .arg(path.as_str())
@@ -214,125 +264,49 @@ fn edit_layout(path: String) -> () {
         .expect("Editor should exist");
 }
 
-fn use_layout(path: &str) -> YAMLConfig {
-    match fs::read_to_string(path) {
-        Ok(file) => serde_yaml::from_str(&file).unwrap(),
-        Err(_) => {
-            println!("{} not found!", path);
-            process::exit(1);
-        }
-    }
-}
+fn current_window_to_pane() -> error::Result<Pane> {
+    let pane_id = env::var("WEZTERM_PANE").map_err(|_| WeztermocilError::MuxUnreachable {
+        reason: String::from("WEZTERM_PANE isn't set, so --here has to be run from inside wezterm"),
+    })?;
 
-fn current_window_to_pane() -> Pane {
-    // I can't see a world where we run this program intentionally outside of wezterm
-    let pane_id = env::var("WEZTERM_PANE").unwrap();
-    Pane {
+    Ok(Pane {
         id: pane_id,
         parent_id: None,
-    }
+    })
 }
 
 fn qualify_layout_file(path: &str) -> String {
-    if path.contains(".yml") == false {
+    if !path.contains(".yml") {
         format!("{}.yml", path)
     } else {
         String::from(path)
     }
 }
 
-fn build_panes(yaml_config: YAMLConfig, starting_pane: Option<&Pane>) -> (FocusTuple, WindowPanes) {
-    let mut focus_tuple = FocusTuple(0, 0);
-    let mut all_panes = vec![];
-    let mut focus_list = vec![];
-
-    if let Some(windows) = yaml_config.windows {
-        for (window_index, window) in windows.iter().enumerate() {
-            focus_list.push(vec![]);
-
-            if window.focus {
-                focus_tuple = FocusTuple(window_index, 0);
-            }
+fn main() {
+    let args = Args::parse();
 
-            let layout =
-                layout_string_to_enum(&window.layout.clone().unwrap_or(String::from("tiled")));
-            let panes = window.panes.clone().unwrap_or(PaneConfig::Commands(vec![]));
-            let main_pane: Pane;
-
-            if starting_pane.is_none() || window_index > 0 {
-                main_pane = match window.root.clone() {
-                    Some(cwd) => Pane::new(Some(&tilde(&cwd))),
-                    None => Pane::new(None),
-                };
-            } else {
-                main_pane = starting_pane.unwrap().clone();
-                let current_dir = std::env::current_dir()
-                    .unwrap()
-                    .into_os_string()
-                    .into_string()
-                    .unwrap();
-
-                let dir = match window.root.clone() {
-                    Some(cwd) => cwd,
-                    None => current_dir,
-                };
-
-                main_pane.run_command(&format!("cd {}", tilde(&dir)));
-                main_pane.run_command("clear");
-            }
+    if args.list {
+        list_layouts();
+        return;
+    }
 
-            if let Some(tab_name) = window.name.clone() {
-                main_pane
-                    .set_tab_title(&tab_name)
-                    .expect("Window name should've been set. Something bad happened here.");
-            }
+    if args.list_layouts {
+        list_builtin_layouts();
+        return;
+    }
 
-            let mut commands = vec![];
-            match panes {
-                PaneConfig::Commands(_commands) => {
-                    for c in _commands {
-                        commands.push(vec![c]);
-                        // If it's just a list of commands, you can't focus a pane
-                        focus_list[window_index].push(false);
-                    }
-                }
-                PaneConfig::Hash(config) => {
-                    for c in config {
-                        if let Some(cmds) = c.commands {
-                            commands.push(cmds);
-                        }
-
-                        focus_list[window_index].push(c.focus);
-                    }
-                }
-            };
-
-            let total_panes = TotalPanes(commands.len());
-
-            all_panes.push(layout.create(total_panes, main_pane).unwrap_or(vec![]));
-
-            for (i, pane) in all_panes[window_index].iter().enumerate() {
-                let command_group = commands.get(i).expect("Pane option should exist!");
-                let should_focus = focus_list[window_index].get(i).expect("Focus should exist");
-
-                if *should_focus == true {
-                    focus_tuple = FocusTuple(window_index, i);
-                }
-
-                for cmd in command_group {
-                    pane.run_command(cmd);
-                }
-            }
+    if !args.validate.is_empty() {
+        if let Err(error) = validate_layouts(&args.validate) {
+            exit_with(error);
         }
+        return;
     }
-    (focus_tuple, WindowPanes(all_panes))
-}
-
-fn main() {
-    let args = Args::parse();
 
-    if args.list {
-        list_layouts();
+    if let Some(layout) = args.preview {
+        if let Err(error) = preview_layout(&layout) {
+            exit_with(error);
+        }
         return;
     }
 
@@ -369,64 +343,61 @@ fn main() {
         return;
     }
 
+    if let Err(error) = launch(args) {
+        exit_with(error);
+    }
+}
+
+fn exit_with(error: WeztermocilError) -> ! {
+    eprintln!("Error: {}", error);
+    process::exit(error.exit_code());
+}
+
+fn launch(args: Args) -> error::Result<()> {
     let mut layout_path: String = String::from("");
 
     if let Some(global_layout) = args.global_layout {
         let layout = qualify_layout_file(&global_layout);
-        let path = get_path_for_layout_file(&layout);
-        match path {
-            Ok(p) => {
-                layout_path = p;
-            }
-            Err(error) => {
-                println!("{}", error);
-                process::exit(1);
-            }
-        }
+        layout_path = get_path_for_layout_file(&layout).map_err(WeztermocilError::Config)?;
     }
 
     if let Some(path) = args.layout {
         let layout = qualify_layout_file(&path);
-        let f = match fs::canonicalize(&tilde(&layout).as_ref()) {
-            Ok(path) => path.into_os_string().into_string(),
-            Err(_) => {
-                println!("Couldn't find file at path: {}. Does it exist?", layout);
-                process::exit(1);
-            }
-        };
-
-        let p = match f {
-            Ok(path) => path,
-            Err(_) => {
-                println!("Couldn't find file at path: {}. Does it exist?", layout);
-                process::exit(1);
-            }
+        let not_found = || {
+            WeztermocilError::Config(format!(
+                "Couldn't find file at path: {}. Does it exist?",
+                layout
+            ))
         };
 
-        layout_path = p;
+        layout_path = fs::canonicalize(tilde(&layout).as_ref())
+            .map_err(|_| not_found())?
+            .into_os_string()
+            .into_string()
+            .map_err(|_| not_found())?;
     }
 
     if layout_path.is_empty() {
         layout_path = String::from("./weztermocil.yml");
     }
 
-    let yaml_config: YAMLConfig = use_layout(&layout_path);
-
-    let main_window = match args.here {
-        true => Some(current_window_to_pane()),
-        false => None,
+    let dry_run_backend = DryRunBackend::new();
+    let backend: &dyn Multiplexer = match args.dry_run {
+        true => &dry_run_backend,
+        false => &WeztermCliBackend,
     };
-    let (focus_tuple, window_panes) = build_panes(yaml_config, main_window.as_ref());
-
-    let focus_pane = window_panes
-        .0
-        .get(focus_tuple.0)
-        .expect("Window to focus should exist!\nIs the layout file empty?")
-        .get(focus_tuple.1)
-        .expect("Pane to focus should exist!\nIs the layout file malformed?");
-
-    match focus_pane.focus() {
-        Ok(res) => res,
-        Err(error) => println!("{:?}", error),
+
+    let mut session = Session::from_file(&layout_path)?
+        .rollback(!args.no_rollback)
+        .dry_run(args.dry_run)
+        .on_event(print_event);
+
+    if args.here {
+        session = match args.dry_run {
+            true => session.here(dry_run_backend.current_pane()),
+            false => session.here(current_window_to_pane()?),
+        };
     }
+
+    session.launch(backend).map(|_| ())
 }
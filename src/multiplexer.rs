@@ -0,0 +1,49 @@
+use crate::{
+    error::Result,
+    wezterm::pane::{PaneSize, Size, SplitDirection},
+};
+
+mod recording;
+mod rollback;
+mod rooted;
+mod tracking;
+
+pub use recording::{Call, RecordingBackend};
+pub use rollback::RollbackBackend;
+pub use rooted::RootedBackend;
+pub use tracking::TrackingBackend;
+
+/// The operations weztermocil needs from a terminal multiplexer.
+///
+/// Panes are addressed by the id the multiplexer hands back from
+/// [`Multiplexer::spawn`] and [`Multiplexer::split_pane`], so an
+/// implementation is free to use whatever id scheme it likes.
+pub trait Multiplexer {
+    /// Open a new tab, optionally in `cwd`, and return the id of its pane.
+    fn spawn(&self, cwd: Option<&str>) -> Result<String>;
+
+    /// Split `pane_id`, optionally starting the new pane in `cwd`, and
+    /// return the id of the newly created pane.
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String>;
+
+    /// How big the pane is, or `None` when the multiplexer can't tell.
+    /// Layouts fall back to percentages for panes of unknown size.
+    fn pane_size(&self, pane_id: &str) -> Result<Option<PaneSize>>;
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()>;
+
+    fn focus(&self, pane_id: &str) -> Result<()>;
+
+    /// Send `text` to the pane as if it were typed, without bracketed paste.
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()>;
+
+    /// Close the pane, and its tab if it was the last pane in it.
+    fn kill_pane(&self, pane_id: &str) -> Result<()>;
+}
@@ -0,0 +1,194 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+
+use crate::{
+    error::Result,
+    wezterm::pane::{PaneSize, Size, SplitDirection},
+};
+
+use super::Multiplexer;
+
+/// A single call made against a [`RecordingBackend`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    Spawn {
+        cwd: Option<String>,
+        new_pane_id: String,
+    },
+    SplitPane {
+        pane_id: String,
+        direction: SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<String>,
+        new_pane_id: String,
+    },
+    SetTabTitle {
+        pane_id: String,
+        title: String,
+    },
+    Focus {
+        pane_id: String,
+    },
+    SendText {
+        pane_id: String,
+        text: String,
+    },
+    KillPane {
+        pane_id: String,
+    },
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Call::Spawn { cwd, new_pane_id } => match cwd {
+                Some(cwd) => write!(f, "spawn --cwd {} => {}", cwd, new_pane_id),
+                None => write!(f, "spawn => {}", new_pane_id),
+            },
+            Call::SplitPane {
+                pane_id,
+                direction,
+                size,
+                top_level,
+                cwd,
+                new_pane_id,
+            } => {
+                write!(f, "split {} {}", pane_id, direction)?;
+                if let Some(size) = size {
+                    write!(f, " {}", size)?;
+                }
+                if *top_level {
+                    write!(f, " top-level")?;
+                }
+                if let Some(cwd) = cwd {
+                    write!(f, " --cwd {}", cwd)?;
+                }
+                write!(f, " => {}", new_pane_id)
+            }
+            Call::SetTabTitle { pane_id, title } => write!(f, "title {} {:?}", pane_id, title),
+            Call::Focus { pane_id } => write!(f, "focus {}", pane_id),
+            Call::SendText { pane_id, text } => write!(f, "send {} {:?}", pane_id, text),
+            Call::KillPane { pane_id } => write!(f, "kill {}", pane_id),
+        }
+    }
+}
+
+/// [`Multiplexer`] that records every call made against it without talking
+/// to a real mux.
+///
+/// Pane ids are allocated sequentially starting at `0`, so the first spawned
+/// pane is always `"0"`. Useful for testing layouts on machines without
+/// wezterm installed.
+///
+/// It doesn't know how big panes are, so layouts split it by percentages.
+/// Wrap it in a [`super::TrackingBackend`] to lay panes out in cells.
+#[derive(Debug, Default)]
+pub struct RecordingBackend {
+    next_id: Cell<usize>,
+    calls: RefCell<Vec<Call>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> RecordingBackend {
+        RecordingBackend::default()
+    }
+
+    /// Every call made so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    /// Only the `split-pane` calls made so far, in order.
+    pub fn splits(&self) -> Vec<Call> {
+        self.calls
+            .borrow()
+            .iter()
+            .filter(|call| matches!(call, Call::SplitPane { .. }))
+            .cloned()
+            .collect()
+    }
+
+    fn allocate_id(&self) -> String {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id.to_string()
+    }
+
+    fn record(&self, call: Call) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+impl Multiplexer for RecordingBackend {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        let new_pane_id = self.allocate_id();
+        self.record(Call::Spawn {
+            cwd: cwd.map(String::from),
+            new_pane_id: new_pane_id.clone(),
+        });
+
+        Ok(new_pane_id)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        let new_pane_id = self.allocate_id();
+        self.record(Call::SplitPane {
+            pane_id: String::from(pane_id),
+            direction: *direction,
+            size,
+            top_level,
+            cwd: cwd.map(String::from),
+            new_pane_id: new_pane_id.clone(),
+        });
+
+        Ok(new_pane_id)
+    }
+
+    fn pane_size(&self, _pane_id: &str) -> Result<Option<PaneSize>> {
+        Ok(None)
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        self.record(Call::SetTabTitle {
+            pane_id: String::from(pane_id),
+            title: String::from(title),
+        });
+
+        Ok(())
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        self.record(Call::Focus {
+            pane_id: String::from(pane_id),
+        });
+
+        Ok(())
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.record(Call::SendText {
+            pane_id: String::from(pane_id),
+            text: String::from(text),
+        });
+
+        Ok(())
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.record(Call::KillPane {
+            pane_id: String::from(pane_id),
+        });
+
+        Ok(())
+    }
+}
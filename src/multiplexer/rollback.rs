@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+
+use crate::{
+    error::Result,
+    wezterm::pane::{PaneSize, Size, SplitDirection},
+};
+
+use super::Multiplexer;
+
+/// [`Multiplexer`] wrapper that remembers every pane created through it, so a
+/// half-built session can be torn down again with [`RollbackBackend::rollback`].
+///
+/// Panes that already existed, like the one used with `--here`, are never
+/// tracked and so are never killed.
+pub struct RollbackBackend<'a> {
+    inner: &'a dyn Multiplexer,
+    created: RefCell<Vec<String>>,
+}
+
+impl<'a> RollbackBackend<'a> {
+    pub fn new(inner: &'a dyn Multiplexer) -> RollbackBackend<'a> {
+        RollbackBackend {
+            inner,
+            created: RefCell::new(vec![]),
+        }
+    }
+
+    /// Ids of the panes created so far, in creation order.
+    pub fn created_panes(&self) -> Vec<String> {
+        self.created.borrow().clone()
+    }
+
+    /// Kill every pane created so far, newest first.
+    ///
+    /// Keeps going when a pane can't be killed so that as much as possible is
+    /// cleaned up, and returns the first error encountered.
+    pub fn rollback(&self) -> Result<()> {
+        let created = self.created.take();
+        let mut first_error = None;
+
+        for pane_id in created.iter().rev() {
+            if let Err(error) = self.inner.kill_pane(pane_id) {
+                first_error.get_or_insert(error);
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn track(&self, pane_id: &str) {
+        self.created.borrow_mut().push(String::from(pane_id));
+    }
+}
+
+impl Multiplexer for RollbackBackend<'_> {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        let pane_id = self.inner.spawn(cwd)?;
+        self.track(&pane_id);
+
+        Ok(pane_id)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        let new_pane_id = self
+            .inner
+            .split_pane(pane_id, direction, size, top_level, cwd)?;
+        self.track(&new_pane_id);
+
+        Ok(new_pane_id)
+    }
+
+    fn pane_size(&self, pane_id: &str) -> Result<Option<PaneSize>> {
+        self.inner.pane_size(pane_id)
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        self.inner.set_tab_title(pane_id, title)
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        self.inner.focus(pane_id)
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.inner.send_text(pane_id, text)
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.inner.kill_pane(pane_id)?;
+        self.created.borrow_mut().retain(|id| id != pane_id);
+
+        Ok(())
+    }
+}
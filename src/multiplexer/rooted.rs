@@ -0,0 +1,72 @@
+use std::cell::Cell;
+
+use crate::{
+    error::Result,
+    wezterm::pane::{PaneSize, Size, SplitDirection},
+};
+
+use super::Multiplexer;
+
+/// [`Multiplexer`] wrapper that starts the panes split off through it in the
+/// directories it was given, the first split in `roots[0]` and so on, so a
+/// layout can create panes without knowing where each of them belongs.
+///
+/// Splits past the end of `roots`, or with a `None` root, are left to
+/// wezterm, and a directory passed to [`Multiplexer::split_pane`] wins.
+pub struct RootedBackend<'a> {
+    inner: &'a dyn Multiplexer,
+    roots: Vec<Option<String>>,
+    splits: Cell<usize>,
+}
+
+impl<'a> RootedBackend<'a> {
+    pub fn new(inner: &'a dyn Multiplexer, roots: Vec<Option<String>>) -> RootedBackend<'a> {
+        RootedBackend {
+            inner,
+            roots,
+            splits: Cell::new(0),
+        }
+    }
+}
+
+impl Multiplexer for RootedBackend<'_> {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        self.inner.spawn(cwd)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        let index = self.splits.get();
+        self.splits.set(index + 1);
+        let root = self.roots.get(index).and_then(Option::as_deref);
+
+        self.inner
+            .split_pane(pane_id, direction, size, top_level, cwd.or(root))
+    }
+
+    fn pane_size(&self, pane_id: &str) -> Result<Option<PaneSize>> {
+        self.inner.pane_size(pane_id)
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        self.inner.set_tab_title(pane_id, title)
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        self.inner.focus(pane_id)
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.inner.send_text(pane_id, text)
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.inner.kill_pane(pane_id)
+    }
+}
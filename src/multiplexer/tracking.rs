@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+
+use crate::{
+    error::{Result, WeztermocilError},
+    geometry::{Rect, Tab},
+    wezterm::pane::{PaneSize, Size, SplitDirection},
+};
+
+use super::Multiplexer;
+
+/// [`Multiplexer`] wrapper that keeps a [`Tab`] for every tab created
+/// through it, so it can tell where each pane ended up.
+///
+/// Tabs are assumed to be `columns` by `rows` cells, and pane sizes are
+/// answered from them. Panes that already existed, like the one used with
+/// `--here`, get a tab of their own the first time they're split. Killed
+/// panes are left in their tab, since nothing is laid out after a pane is
+/// killed.
+pub struct TrackingBackend<'a> {
+    inner: &'a dyn Multiplexer,
+    columns: usize,
+    rows: usize,
+    /// Whether tabs really are `columns` by `rows`, rather than big enough
+    /// to tell where panes are relative to each other.
+    sized: bool,
+    tabs: RefCell<Vec<Tab>>,
+}
+
+impl<'a> TrackingBackend<'a> {
+    /// The size of the tabs of a [`TrackingBackend::relative`] backend. It
+    /// only has to be big enough for every split to fit.
+    const RELATIVE_COLUMNS: usize = 10_000;
+    const RELATIVE_ROWS: usize = 10_000;
+
+    pub fn new(inner: &'a dyn Multiplexer, columns: usize, rows: usize) -> TrackingBackend<'a> {
+        TrackingBackend {
+            inner,
+            columns,
+            rows,
+            sized: true,
+            tabs: RefCell::new(vec![]),
+        }
+    }
+
+    /// A backend for tabs of unknown size, which tells where panes are
+    /// relative to each other and leaves pane sizes to `inner`.
+    pub fn relative(inner: &'a dyn Multiplexer) -> TrackingBackend<'a> {
+        TrackingBackend {
+            sized: false,
+            ..TrackingBackend::new(
+                inner,
+                TrackingBackend::RELATIVE_COLUMNS,
+                TrackingBackend::RELATIVE_ROWS,
+            )
+        }
+    }
+
+    /// Where the pane is in its tab, if it was created or split through
+    /// this backend.
+    pub fn rect(&self, pane_id: &str) -> Option<Rect> {
+        self.tabs.borrow().iter().find_map(|tab| tab.rect(pane_id))
+    }
+}
+
+impl Multiplexer for TrackingBackend<'_> {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        let pane_id = self.inner.spawn(cwd)?;
+        self.tabs
+            .borrow_mut()
+            .push(Tab::new(&pane_id, self.columns, self.rows));
+
+        Ok(pane_id)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        let new_pane_id = self
+            .inner
+            .split_pane(pane_id, direction, size, top_level, cwd)?;
+
+        let mut tabs = self.tabs.borrow_mut();
+        let index = match tabs.iter().position(|tab| tab.contains(pane_id)) {
+            Some(index) => index,
+            None => {
+                tabs.push(Tab::new(pane_id, self.columns, self.rows));
+                tabs.len() - 1
+            }
+        };
+        tabs[index]
+            .split(pane_id, direction, size, top_level, &new_pane_id)
+            .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+
+        Ok(new_pane_id)
+    }
+
+    fn pane_size(&self, pane_id: &str) -> Result<Option<PaneSize>> {
+        if !self.sized {
+            return self.inner.pane_size(pane_id);
+        }
+
+        // A pane that isn't tracked yet gets a whole tab once it's split.
+        let rect = self.rect(pane_id);
+        Ok(Some(PaneSize {
+            columns: rect.map_or(self.columns, |rect| rect.width),
+            rows: rect.map_or(self.rows, |rect| rect.height),
+        }))
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        self.inner.set_tab_title(pane_id, title)
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        self.inner.focus(pane_id)
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.inner.send_text(pane_id, text)
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.inner.kill_pane(pane_id)
+    }
+}
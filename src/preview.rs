@@ -0,0 +1,196 @@
+use std::fmt::Write;
+
+use crate::{
+    config::YAMLConfig,
+    error::{Result, WeztermocilError},
+    geometry::Rect,
+    layout::{LayoutRegistry, TotalPanes},
+    multiplexer::{Multiplexer, RecordingBackend, TrackingBackend},
+    wezterm::pane::Pane,
+};
+
+/// The size of the tab layouts are previewed in, in cells.
+pub const COLUMNS: usize = 120;
+pub const ROWS: usize = 40;
+
+/// Lay every window of `config` out on a `columns` by `rows` tab and draw
+/// them as boxes, the way the README does, with each pane's number, its
+/// first command and which pane gets focus.
+pub fn render(config: &YAMLConfig, columns: usize, rows: usize) -> Result<String> {
+    render_with(config, &LayoutRegistry::new(), columns, rows)
+}
+
+/// Like [`render`], with the layouts in `layouts` on top of the built-in
+/// ones, the way [`Session::layouts`](crate::Session::layouts) launches them.
+pub fn render_with(
+    config: &YAMLConfig,
+    layouts: &LayoutRegistry,
+    columns: usize,
+    rows: usize,
+) -> Result<String> {
+    config.validate_with(layouts)?;
+
+    let recording = RecordingBackend::new();
+    let backend = TrackingBackend::new(&recording, columns, rows);
+    let windows = config.windows.as_deref().unwrap_or_default();
+
+    let mut laid_out = vec![];
+    let mut focus = (0, 0);
+    for (window_index, window) in windows.iter().enumerate() {
+        let options = window
+            .layout_options()
+            .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+        let panes = window.pane_configs();
+        let labels = match window.parsed_diagram() {
+            Some(Ok(diagram)) => diagram.labels,
+            _ => (0..panes.len()).collect(),
+        };
+
+        if window.focus {
+            focus = (window_index, 0);
+        }
+        if let Some(index) = panes.iter().rposition(|(_, pane)| pane.focus()) {
+            focus = (window_index, index);
+        }
+
+        let main_pane = Pane::new(&backend, None)?;
+        let size = backend.pane_size(&main_pane.id)?;
+        let name = window.layout_name(size);
+        let layout = layouts
+            .resolve(name)
+            .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+        let created = layout.create(&backend, TotalPanes(panes.len()), main_pane, &options)?;
+        let cells = created
+            .iter()
+            .enumerate()
+            .map(|(index, pane)| {
+                let label = labels.get(index).copied().unwrap_or(index);
+                let command = panes
+                    .get(index)
+                    .and_then(|(_, pane)| pane.commands().into_iter().next());
+                let rect = backend.rect(&pane.id).ok_or_else(|| {
+                    WeztermocilError::Config(format!(
+                        "`{}` returned pane {}, which it didn't create",
+                        name, pane.id
+                    ))
+                })?;
+                Ok((rect, label, command))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        laid_out.push((name, cells));
+    }
+
+    let mut output = String::new();
+    for (window_index, (name, cells)) in laid_out.into_iter().enumerate() {
+        if window_index > 0 {
+            output.push('\n');
+        }
+
+        match &windows[window_index].name {
+            Some(window_name) => {
+                writeln!(
+                    output,
+                    "windows[{}] {}: {}",
+                    window_index, window_name, name
+                )
+            }
+            None => writeln!(output, "windows[{}]: {}", window_index, name),
+        }
+        .unwrap();
+
+        let mut canvas = Canvas::new(columns, rows);
+        for (index, (rect, label, command)) in cells.into_iter().enumerate() {
+            let mut title = format!("({})", label);
+            if focus == (window_index, index) {
+                title.push_str(" <focus here>");
+            }
+            canvas.draw(rect, &[Some(title), command]);
+        }
+        output.push_str(&canvas.render());
+    }
+
+    Ok(output)
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// Characters for a tab of `columns` by `rows` cells, with room for the
+/// outline around it.
+struct Canvas {
+    /// Which ways lines go from each position.
+    lines: Vec<Vec<u8>>,
+    text: Vec<Vec<Option<char>>>,
+}
+
+impl Canvas {
+    fn new(columns: usize, rows: usize) -> Canvas {
+        Canvas {
+            lines: vec![vec![0; columns + 2]; rows + 2],
+            text: vec![vec![None; columns + 2]; rows + 2],
+        }
+    }
+
+    /// Draw a box around `rect` and write `lines` of text inside it, cut
+    /// short when they don't fit.
+    fn draw(&mut self, rect: Rect, lines: &[Option<String>]) {
+        let (left, top) = (rect.left, rect.top);
+        let (right, bottom) = (rect.left + rect.width + 1, rect.top + rect.height + 1);
+
+        for column in left..right {
+            for row in [top, bottom] {
+                self.lines[row][column] |= RIGHT;
+                self.lines[row][column + 1] |= LEFT;
+            }
+        }
+        for row in top..bottom {
+            for column in [left, right] {
+                self.lines[row][column] |= DOWN;
+                self.lines[row + 1][column] |= UP;
+            }
+        }
+
+        for (offset, line) in lines.iter().enumerate().take(rect.height) {
+            let Some(line) = line else { continue };
+            let row = top + 1 + offset;
+            let room = rect.width.saturating_sub(2);
+            for (column, c) in (left + 2..).zip(line.chars().take(room)) {
+                self.text[row][column] = Some(c);
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let last_row = self.lines.len() - 1;
+        let mut output = String::new();
+
+        for (row, lines) in self.lines.iter().enumerate() {
+            let line = lines
+                .iter()
+                .zip(&self.text[row])
+                .map(|(lines, text)| {
+                    let vertical = lines & (UP | DOWN) != 0;
+                    let horizontal = lines & (LEFT | RIGHT) != 0;
+
+                    match (vertical, horizontal) {
+                        (false, false) => text.unwrap_or(' '),
+                        (true, false) => '|',
+                        (false, true) => '-',
+                        (true, true) if lines & UP != 0 && lines & DOWN != 0 => '|',
+                        (true, true) if row == 0 => '.',
+                        (true, true) if row == last_row => '\'',
+                        (true, true) => '-',
+                    }
+                })
+                .collect::<String>();
+
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+
+        output
+    }
+}
@@ -0,0 +1,346 @@
+use std::{fs, path::Path, process::Command};
+
+use crate::{
+    config::{PreConfig, RootError, WindowConfig, YAMLConfig},
+    error::{Result, WeztermocilError},
+    format::shell_quote,
+    layout::{LayoutOptions, LayoutRegistry, LayoutStrategy, TotalPanes},
+    multiplexer::{Multiplexer, RecordingBackend, RollbackBackend, RootedBackend, TrackingBackend},
+    wezterm::pane::{Pane, PaneSize},
+};
+
+type PaneIndex = usize;
+type WindowIndex = usize;
+struct FocusTuple(WindowIndex, PaneIndex);
+
+/// Something a [`Session`] does while launching that's worth telling the
+/// user about. See [`Session::on_event`].
+#[derive(Debug)]
+pub enum SessionEvent<'a> {
+    /// With `dry_run`, a `pre` command that would have been run with `sh -c`.
+    WouldRunPre { command: &'a str },
+    /// With `dry_run`, a missing root that would have been created.
+    WouldCreateRoot { dir: &'a str },
+    /// Launching failed, and the panes created so far are being closed.
+    RollingBack { panes: usize },
+    /// Some of the panes created so far couldn't be closed.
+    RollbackFailed { error: &'a WeztermocilError },
+}
+
+/// Launches a [`YAMLConfig`] against a [`Multiplexer`].
+///
+/// ```no_run
+/// use weztermocil::{
+///     config::{PaneConfigOptions, WindowConfig, YAMLConfig},
+///     wezterm::WeztermCliBackend,
+///     Session,
+/// };
+///
+/// let config = YAMLConfig::new().window(
+///     WindowConfig::new()
+///         .name("editor")
+///         .root("~/code/project")
+///         .layout("main-vertical")
+///         .pane("vim")
+///         .pane(PaneConfigOptions::new().command("git status").focus()),
+/// );
+///
+/// Session::new(config).launch(&WeztermCliBackend).unwrap();
+/// ```
+pub struct Session {
+    config: YAMLConfig,
+    here: Option<Pane>,
+    rollback: bool,
+    dry_run: bool,
+    layouts: LayoutRegistry,
+    on_event: Box<dyn Fn(&SessionEvent)>,
+}
+
+impl Session {
+    pub fn new(config: YAMLConfig) -> Session {
+        Session {
+            config,
+            here: None,
+            rollback: true,
+            dry_run: false,
+            layouts: LayoutRegistry::new(),
+            on_event: Box::new(|_| {}),
+        }
+    }
+
+    /// Load the session from the layout file at `path`.
+    pub fn from_file(path: &str) -> Result<Session> {
+        Ok(Session::new(YAMLConfig::from_file(path)?))
+    }
+
+    /// Use `pane` as the session's first window instead of spawning a new tab.
+    pub fn here(mut self, pane: Pane) -> Session {
+        self.here = Some(pane);
+        self
+    }
+
+    /// Whether to close the panes created so far when launching fails.
+    /// Defaults to `true`.
+    pub fn rollback(mut self, rollback: bool) -> Session {
+        self.rollback = rollback;
+        self
+    }
+
+    /// Report the `pre` commands and the roots to create as
+    /// [`SessionEvent`]s instead of running them. Meant to be paired with a
+    /// backend that doesn't touch a running mux either.
+    pub fn dry_run(mut self, dry_run: bool) -> Session {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Let windows use the layouts in `layouts` on top of the built-in ones.
+    pub fn layouts(mut self, layouts: LayoutRegistry) -> Session {
+        self.layouts = layouts;
+        self
+    }
+
+    /// Call `on_event` with every [`SessionEvent`] while launching. They're
+    /// dropped by default.
+    pub fn on_event(mut self, on_event: impl Fn(&SessionEvent) + 'static) -> Session {
+        self.on_event = Box::new(on_event);
+        self
+    }
+
+    pub fn config(&self) -> &YAMLConfig {
+        &self.config
+    }
+
+    /// Validate the config, run its `pre` commands and create every window.
+    ///
+    /// Returns the panes of each window, in the order of
+    /// [`WindowConfig::pane_configs`](crate::config::WindowConfig::pane_configs).
+    /// Either the whole session is created or, unless rollback was turned
+    /// off, none of it is left behind.
+    pub fn launch(&self, backend: &dyn Multiplexer) -> Result<Vec<Vec<Pane>>> {
+        self.config.validate_with(&self.layouts)?;
+
+        if let Some(pre) = &self.config.pre {
+            self.run_pre(pre)?;
+        }
+        self.create_roots()?;
+
+        let rollback = RollbackBackend::new(backend);
+        let result = self
+            .build_panes(&rollback)
+            .and_then(|(focus_tuple, window_panes)| {
+                Session::focus(&rollback, focus_tuple, &window_panes)?;
+                Ok(window_panes)
+            });
+
+        if result.is_err() && self.rollback {
+            let created = rollback.created_panes();
+            if !created.is_empty() {
+                (self.on_event)(&SessionEvent::RollingBack {
+                    panes: created.len(),
+                });
+            }
+            if let Err(error) = rollback.rollback() {
+                (self.on_event)(&SessionEvent::RollbackFailed { error: &error });
+            }
+        }
+
+        result
+    }
+
+    /// Run the session's `pre` commands on the host, in order, stopping at the
+    /// first one that fails. With `dry_run` they're only reported.
+    fn run_pre(&self, pre: &PreConfig) -> Result<()> {
+        for command in pre.commands() {
+            if self.dry_run {
+                (self.on_event)(&SessionEvent::WouldRunPre { command: &command });
+                continue;
+            }
+
+            let status = Command::new("sh").arg("-c").arg(&command).status()?;
+
+            if !status.success() {
+                return Err(WeztermocilError::PreFailed {
+                    command,
+                    status: status.code(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create the missing roots of windows with `create_root`. With
+    /// `dry_run` they're only reported.
+    fn create_roots(&self) -> Result<()> {
+        let windows = self.config.windows.as_deref().unwrap_or_default();
+        for window in windows.iter().filter(|window| window.create_root) {
+            for (_, root) in window.roots(self.config.base_dir.as_deref()) {
+                let root = root.map_err(|e| WeztermocilError::Config(e.to_string()))?;
+                if Path::new(&root).is_dir() {
+                    continue;
+                }
+
+                if self.dry_run {
+                    (self.on_event)(&SessionEvent::WouldCreateRoot { dir: &root });
+                    continue;
+                }
+
+                fs::create_dir_all(&root)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_panes(&self, backend: &dyn Multiplexer) -> Result<(FocusTuple, Vec<Vec<Pane>>)> {
+        let mut focus_tuple = FocusTuple(0, 0);
+        let mut all_panes = vec![];
+
+        let windows = self.config.windows.as_deref().unwrap_or_default();
+        for (window_index, window) in windows.iter().enumerate() {
+            if window.focus {
+                focus_tuple = FocusTuple(window_index, 0);
+            }
+
+            let options = window
+                .layout_options()
+                .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+            let panes = window.pane_configs();
+            let root = window
+                .root_dir(self.config.base_dir.as_deref())
+                .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+            let main_pane: Pane;
+
+            if let Some(pane) = self.here.as_ref().filter(|_| window_index == 0) {
+                main_pane = pane.clone();
+                let current_dir = std::env::current_dir()?.to_string_lossy().to_string();
+
+                let dir = root.clone().unwrap_or(current_dir);
+
+                main_pane.run_command(backend, &format!("cd {}", shell_quote(&dir)))?;
+                main_pane.run_command(backend, "clear")?;
+            } else {
+                main_pane = Pane::new(backend, root.as_deref())?;
+            }
+
+            if let Some(tab_name) = window.name.clone() {
+                main_pane.set_tab_title(backend, &tab_name)?;
+            }
+
+            // Breakpoints pick the layout by how big the window turned out,
+            // and pane roots are matched to panes by where they end up.
+            let size = match window.has_breakpoints() || window.has_pane_roots() {
+                true => backend.pane_size(&main_pane.id)?,
+                false => None,
+            };
+            let layout = self
+                .layouts
+                .resolve(window.layout_name(size))
+                .map_err(|e| WeztermocilError::Config(e.to_string()))?;
+            let total_panes = TotalPanes(panes.len());
+
+            let mut roots = match window.has_pane_roots() {
+                true => self.pane_roots(window, layout.as_ref(), total_panes, &options, size)?,
+                false => vec![],
+            };
+            // The first pane already exists, so it can only be moved with `cd`.
+            if !roots.is_empty() {
+                let first_root = roots.remove(0);
+                if let Some(first_root) =
+                    first_root.filter(|first_root| Some(first_root) != root.as_ref())
+                {
+                    main_pane.run_command(backend, &format!("cd {}", shell_quote(&first_root)))?;
+                }
+            }
+
+            let rooted = RootedBackend::new(backend, roots);
+            all_panes.push(layout.create(&rooted, total_panes, main_pane, &options)?);
+
+            let window_commands = window.window_commands();
+
+            for (i, pane) in all_panes[window_index].iter().enumerate() {
+                // Layouts can create more panes than there are pane configs,
+                // like `3_columns` with fewer than 3 panes. Those are left empty.
+                let (commands, should_focus) = match panes.get(i) {
+                    Some((_, config)) => (config.commands(), config.focus()),
+                    None => (vec![], false),
+                };
+
+                if should_focus {
+                    focus_tuple = FocusTuple(window_index, i);
+                }
+
+                for cmd in window_commands.iter().chain(&commands) {
+                    pane.run_command(backend, cmd)?;
+                }
+            }
+        }
+
+        Ok((focus_tuple, all_panes))
+    }
+
+    /// The directory each pane of the window starts in, in the order
+    /// `layout` creates them, starting with the window's first pane.
+    ///
+    /// Layouts hand panes out in their own order, so the layout is run
+    /// against a [`RecordingBackend`] first to find out which pane config
+    /// each of the panes it creates ends up with.
+    fn pane_roots(
+        &self,
+        window: &WindowConfig,
+        layout: &dyn LayoutStrategy,
+        total_panes: TotalPanes,
+        options: &LayoutOptions,
+        size: Option<PaneSize>,
+    ) -> Result<Vec<Option<String>>> {
+        let recording = RecordingBackend::new();
+        let tracking;
+        let rehearsal: &dyn Multiplexer = match size {
+            Some(size) => {
+                tracking = TrackingBackend::new(&recording, size.columns, size.rows);
+                &tracking
+            }
+            None => &recording,
+        };
+
+        let starting_pane = Pane::new(rehearsal, None)?;
+        let created = layout.create(rehearsal, total_panes, starting_pane, options)?;
+
+        let base_dir = self.config.base_dir.as_deref();
+        let config_error = |e: RootError| WeztermocilError::Config(e.to_string());
+
+        // Recorded pane ids count up from 0 in creation order.
+        let mut roots = vec![window.root_dir(base_dir).map_err(config_error)?; created.len()];
+        for ((_, config), pane) in window.pane_configs().iter().zip(&created) {
+            let index = pane.id.parse::<usize>().ok();
+            if let Some(root) = index.and_then(|index| roots.get_mut(index)) {
+                *root = window.pane_root(config, base_dir).map_err(config_error)?;
+            }
+        }
+
+        Ok(roots)
+    }
+
+    fn focus(
+        backend: &dyn Multiplexer,
+        focus_tuple: FocusTuple,
+        window_panes: &[Vec<Pane>],
+    ) -> Result<()> {
+        let focus_pane = window_panes
+            .get(focus_tuple.0)
+            .ok_or_else(|| {
+                WeztermocilError::Config(String::from(
+                    "Window to focus doesn't exist. Is the layout file empty?",
+                ))
+            })?
+            .get(focus_tuple.1)
+            .ok_or_else(|| {
+                WeztermocilError::Config(String::from(
+                    "Pane to focus doesn't exist. Is the layout file malformed?",
+                ))
+            })?;
+
+        focus_pane.focus(backend)
+    }
+}
@@ -1,2 +1,6 @@
 mod cli;
+mod dry_run;
 pub mod pane;
+
+pub use cli::{command_failure, parse_pane_id, parse_pane_size, spawn_failure, WeztermCliBackend};
+pub use dry_run::DryRunBackend;
@@ -1,29 +1,104 @@
 use std::{
-    error::Error,
+    io::{self, ErrorKind, Write},
     process::{Command, Stdio},
-    str::from_utf8,
 };
 
-use crate::{format::strip_trailing_newline, WEZTERM_CLI};
+use serde::Deserialize;
 
-use super::pane::SplitDirection;
+use crate::{
+    error::{Result, WeztermocilError},
+    format::strip_trailing_newline,
+    multiplexer::Multiplexer,
+    WEZTERM_CLI,
+};
+
+use super::pane::{PaneSize, Size, SplitDirection};
+
+/// A pane as `wezterm cli list --format json` describes it, leaving out
+/// everything weztermocil doesn't need.
+#[derive(Deserialize)]
+struct ListedPane {
+    pane_id: u64,
+    size: ListedSize,
+}
+
+#[derive(Deserialize)]
+struct ListedSize {
+    rows: usize,
+    cols: usize,
+}
+
+/// The error for `wezterm` failing to start at all.
+pub fn spawn_failure(error: io::Error) -> WeztermocilError {
+    match error.kind() {
+        ErrorKind::NotFound => WeztermocilError::BinaryMissing {
+            binary: String::from(WEZTERM_CLI),
+        },
+        _ => WeztermocilError::Io(error),
+    }
+}
+
+/// The error for `wezterm` exiting with `status` after being run with
+/// `args`, going by what it printed to `stderr`.
+pub fn command_failure(args: &[String], status: Option<i32>, stderr: String) -> WeztermocilError {
+    // wezterm doesn't give us a dedicated exit code for this,
+    // but every flavour of the message mentions connecting.
+    if stderr.to_lowercase().contains("connect") {
+        return WeztermocilError::MuxUnreachable { reason: stderr };
+    }
+
+    WeztermocilError::CommandFailed {
+        command: format!("{} {}", WEZTERM_CLI, args.join(" ")),
+        status,
+        stderr,
+    }
+}
+
+/// The pane id `wezterm cli spawn` and `split-pane` print.
+pub fn parse_pane_id(output: &str) -> Result<String> {
+    let pane_id = strip_trailing_newline(output);
+
+    match pane_id.parse::<u64>() {
+        Ok(_) => Ok(String::from(pane_id)),
+        Err(_) => Err(WeztermocilError::BadPaneId {
+            output: String::from(output),
+        }),
+    }
+}
+
+/// Find the size of `pane_id` in the output of `wezterm cli list`.
+/// JSON is a subset of YAML, so it's read with the same parser as
+/// layout files.
+pub fn parse_pane_size(output: &str, pane_id: &str) -> Option<PaneSize> {
+    let panes: Vec<ListedPane> = serde_yaml::from_str(output).ok()?;
+
+    panes
+        .into_iter()
+        .find(|pane| pane.pane_id.to_string() == pane_id)
+        .map(|pane| PaneSize {
+            columns: pane.size.cols,
+            rows: pane.size.rows,
+        })
+}
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct CLI {}
 
 impl CLI {
     // TODO: Add support for Windows
-    fn new() -> Command {
+    fn command() -> Command {
         Command::new(WEZTERM_CLI)
     }
 
-    pub fn split_pane(
+    pub(super) fn split_pane_args(
         pane_id: &str,
         direction: &SplitDirection,
-        percentage: Option<&str>,
+        size: Option<Size>,
         top_level: bool,
-    ) -> Result<String, Box<dyn Error>> {
-        let mut cmd = CLI::new();
+        cwd: Option<&str>,
+    ) -> Vec<String> {
         let mut commands = vec!["cli", "split-pane", "--pane-id", pane_id];
+        let amount;
 
         let dir = match direction {
             SplitDirection::Right => "--right",
@@ -33,71 +108,192 @@ impl CLI {
         };
         commands.push(dir);
 
-        if let Some(p) = percentage {
-            commands.push("--percent");
-            commands.push(p);
+        if let Some(size) = size {
+            let (flag, value) = match size {
+                Size::Percent(percent) => ("--percent", percent.to_string()),
+                Size::Cells(cells) => ("--cells", cells.to_string()),
+            };
+            amount = value;
+            commands.push(flag);
+            commands.push(&amount);
         }
 
         if top_level {
             commands.push("--top-level");
         }
 
-        // println!("cmds: {:?}", commands);
-        let output = cmd.args(commands).output().expect("Failed to create pane");
-        // TODO: Handle non-zero case from call to `.output()` properly
-        // currently it's being swallowed.
-        let pane_id = from_utf8(&output.stdout).expect("Failed to convert from utf8");
-        // println!("output: {:?}", output);
+        if let Some(dir) = cwd {
+            commands.push("--cwd");
+            commands.push(dir);
+        }
 
-        Ok(String::from(strip_trailing_newline(pane_id)))
+        commands.into_iter().map(String::from).collect()
     }
 
-    pub fn spawn(cwd: Option<&str>) -> Result<String, Box<dyn Error>> {
-        let mut cmd = CLI::new();
+    pub(super) fn spawn_args(cwd: Option<&str>) -> Vec<String> {
         let mut commands = vec!["cli", "spawn"];
 
-        match cwd {
-            Some(dir) => {
-                commands.push("--cwd");
-                commands.push(dir);
-            }
-            None => (),
-        };
+        if let Some(dir) = cwd {
+            commands.push("--cwd");
+            commands.push(dir);
+        }
 
-        let output = cmd.args(commands).output()?;
-        let tab_id = from_utf8(&output.stdout)?;
+        commands.into_iter().map(String::from).collect()
+    }
 
-        Ok(String::from(strip_trailing_newline(tab_id)))
+    pub(super) fn set_tab_title_args(pane_id: &str, title: &str) -> Vec<String> {
+        ["cli", "set-tab-title", title, "--pane-id", pane_id]
+            .into_iter()
+            .map(String::from)
+            .collect()
     }
 
-    pub fn set_tab_title(pane_id: &str, title: &str) -> Result<(), Box<dyn Error>> {
-        let mut cmd = CLI::new();
-        let commands = vec!["cli", "set-tab-title", title, "--pane-id", pane_id];
+    pub(super) fn focus_args(pane_id: &str) -> Vec<String> {
+        ["cli", "activate-pane", "--pane-id", pane_id]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
 
-        cmd.args(commands).output()?;
+    pub(super) fn list_args() -> Vec<String> {
+        ["cli", "list", "--format", "json"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    pub(super) fn send_text_args(pane_id: &str) -> Vec<String> {
+        ["cli", "send-text", "--pane-id", pane_id, "--no-paste"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Run `wezterm` with `args`, feeding it `stdin` if given, and return
+    /// its stdout once it has exited successfully.
+    fn run(args: Vec<String>, stdin: Option<&str>) -> Result<String> {
+        let mut child = CLI::command()
+            .args(&args)
+            .stdin(match stdin {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_failure)?;
+
+        // `send-text` reads the text from stdin until it's closed,
+        // so the handle has to be dropped before waiting on the child.
+        if let (Some(text), Some(mut handle)) = (stdin, child.stdin.take()) {
+            handle.write_all(text.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+        if !output.status.success() {
+            return Err(command_failure(&args, output.status.code(), stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    pub(super) fn kill_pane_args(pane_id: &str) -> Vec<String> {
+        ["cli", "kill-pane", "--pane-id", pane_id]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    pub fn split_pane(
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        let commands = CLI::split_pane_args(pane_id, direction, size, top_level, cwd);
+        let output = CLI::run(commands, None)?;
+
+        parse_pane_id(&output)
+    }
+
+    pub fn spawn(cwd: Option<&str>) -> Result<String> {
+        let output = CLI::run(CLI::spawn_args(cwd), None)?;
+
+        parse_pane_id(&output)
+    }
+
+    /// The size of `pane_id`, or `None` when wezterm doesn't list it.
+    pub fn pane_size(pane_id: &str) -> Result<Option<PaneSize>> {
+        let output = CLI::run(CLI::list_args(), None)?;
+
+        Ok(parse_pane_size(&output, pane_id))
+    }
+
+    pub fn set_tab_title(pane_id: &str, title: &str) -> Result<()> {
+        CLI::run(CLI::set_tab_title_args(pane_id, title), None)?;
 
         Ok(())
     }
 
-    pub fn focus(pane_id: &str) -> Result<(), Box<dyn Error>> {
-        CLI::new()
-            .args(["cli", "activate-pane", "--pane-id", pane_id])
-            .output()?;
+    pub fn focus(pane_id: &str) -> Result<()> {
+        CLI::run(CLI::focus_args(pane_id), None)?;
 
         Ok(())
     }
 
-    pub fn run_command(pane_id: &str, command: &str) -> () {
-        let cmd = Command::new("echo")
-            .arg(command)
-            .stdout(Stdio::piped())
-            .spawn()
-            .unwrap();
+    pub fn send_text(pane_id: &str, text: &str) -> Result<()> {
+        CLI::run(CLI::send_text_args(pane_id), Some(text))?;
 
-        CLI::new()
-            .args(["cli", "send-text", "--pane-id", pane_id, "--no-paste"])
-            .stdin(Stdio::from(cmd.stdout.unwrap()))
-            .spawn()
-            .expect("Failed to send command");
+        Ok(())
+    }
+
+    pub fn kill_pane(pane_id: &str) -> Result<()> {
+        CLI::run(CLI::kill_pane_args(pane_id), None)?;
+
+        Ok(())
+    }
+}
+
+/// [`Multiplexer`] that drives a running wezterm through `wezterm cli`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WeztermCliBackend;
+
+impl Multiplexer for WeztermCliBackend {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        CLI::spawn(cwd)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        CLI::split_pane(pane_id, direction, size, top_level, cwd)
+    }
+
+    fn pane_size(&self, pane_id: &str) -> Result<Option<PaneSize>> {
+        CLI::pane_size(pane_id)
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        CLI::set_tab_title(pane_id, title)
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        CLI::focus(pane_id)
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        CLI::send_text(pane_id, text)
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        CLI::kill_pane(pane_id)
     }
 }
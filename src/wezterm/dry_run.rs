@@ -0,0 +1,152 @@
+use std::cell::{Cell, RefCell};
+
+use crate::{
+    error::Result,
+    format::{shell_quote, strip_trailing_newline},
+    multiplexer::Multiplexer,
+    preview, WEZTERM_CLI,
+};
+
+use super::{
+    cli::CLI,
+    pane::{Pane, PaneSize, Size, SplitDirection},
+};
+
+/// [`Multiplexer`] that prints the `wezterm cli` invocations a layout would
+/// make instead of running them.
+///
+/// Pane ids are handed out from a counter so that later commands can refer to
+/// panes created earlier in the run, the same way they would against a real mux.
+/// Nothing is there to be measured, so every tab is taken to be as big as
+/// the ones `--preview` draws, and splits are printed in cells for that size.
+#[derive(Debug)]
+pub struct DryRunBackend {
+    next_id: Cell<usize>,
+    lines: RefCell<Vec<String>>,
+    echo: bool,
+}
+
+impl DryRunBackend {
+    /// Id of the pane standing in for the one weztermocil was started from.
+    const CURRENT_PANE_ID: usize = 0;
+
+    pub fn new() -> DryRunBackend {
+        DryRunBackend {
+            next_id: Cell::new(DryRunBackend::CURRENT_PANE_ID + 1),
+            lines: RefCell::new(vec![]),
+            echo: true,
+        }
+    }
+
+    /// Like [`DryRunBackend::new`], but only keeps the lines for
+    /// [`DryRunBackend::lines`] instead of printing them.
+    pub fn buffered() -> DryRunBackend {
+        DryRunBackend {
+            echo: false,
+            ..DryRunBackend::new()
+        }
+    }
+
+    /// Every line printed so far, in order.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.borrow().clone()
+    }
+
+    /// A stand-in for the pane weztermocil was launched from, used with `--here`.
+    pub fn current_pane(&self) -> Pane {
+        Pane {
+            id: DryRunBackend::CURRENT_PANE_ID.to_string(),
+            parent_id: None,
+        }
+    }
+
+    fn allocate_id(&self) -> String {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id.to_string()
+    }
+
+    fn print(&self, stdin: Option<&str>, args: &[String], note: Option<String>) {
+        let mut line = match stdin {
+            Some(text) => format!("echo {} | ", shell_quote(strip_trailing_newline(text))),
+            None => String::new(),
+        };
+        line.push_str(WEZTERM_CLI);
+        for arg in args {
+            line.push(' ');
+            line.push_str(&shell_quote(arg));
+        }
+        if let Some(note) = note {
+            line = format!("{}  # {}", line, note);
+        }
+
+        if self.echo {
+            println!("{}", line);
+        }
+        self.lines.borrow_mut().push(line);
+    }
+}
+
+impl Default for DryRunBackend {
+    fn default() -> Self {
+        DryRunBackend::new()
+    }
+}
+
+impl Multiplexer for DryRunBackend {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        let id = self.allocate_id();
+        self.print(None, &CLI::spawn_args(cwd), Some(format!("=> pane {}", id)));
+
+        Ok(id)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        let id = self.allocate_id();
+        self.print(
+            None,
+            &CLI::split_pane_args(pane_id, direction, size, top_level, cwd),
+            Some(format!("=> pane {} (parent {})", id, pane_id)),
+        );
+
+        Ok(id)
+    }
+
+    fn pane_size(&self, _pane_id: &str) -> Result<Option<PaneSize>> {
+        Ok(Some(PaneSize {
+            columns: preview::COLUMNS,
+            rows: preview::ROWS,
+        }))
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        self.print(None, &CLI::set_tab_title_args(pane_id, title), None);
+
+        Ok(())
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        self.print(None, &CLI::focus_args(pane_id), None);
+
+        Ok(())
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.print(Some(text), &CLI::send_text_args(pane_id), None);
+
+        Ok(())
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.print(None, &CLI::kill_pane_args(pane_id), None);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
-use std::error::Error;
+use std::{error::Error, fmt, str::FromStr};
 
-use super::cli::CLI;
+use crate::{error::Result, multiplexer::Multiplexer};
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SplitDirection {
     Right,
     Left,
@@ -10,6 +10,107 @@ pub enum SplitDirection {
     Top,
 }
 
+impl fmt::Display for SplitDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SplitDirection::Right => "right",
+            SplitDirection::Left => "left",
+            SplitDirection::Bottom => "bottom",
+            SplitDirection::Top => "top",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl SplitDirection {
+    pub fn opposite(&self) -> SplitDirection {
+        match self {
+            SplitDirection::Right => SplitDirection::Left,
+            SplitDirection::Left => SplitDirection::Right,
+            SplitDirection::Bottom => SplitDirection::Top,
+            SplitDirection::Top => SplitDirection::Bottom,
+        }
+    }
+}
+
+/// How big the pane created by a split should be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// A percentage of the pane being split, from 1 to 99.
+    Percent(u8),
+    /// A number of terminal cells: columns for left and right splits,
+    /// rows for top and bottom ones.
+    Cells(usize),
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Size::Percent(percent) => write!(f, "{}%", percent),
+            Size::Cells(cells) => write!(f, "{}c", cells),
+        }
+    }
+}
+
+/// How big a pane is, in terminal cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneSize {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl PaneSize {
+    /// How many cells a split in `direction` has to divide: columns for
+    /// left and right splits, rows for top and bottom ones.
+    pub fn along(&self, direction: SplitDirection) -> usize {
+        match direction {
+            SplitDirection::Right | SplitDirection::Left => self.columns,
+            SplitDirection::Bottom | SplitDirection::Top => self.rows,
+        }
+    }
+}
+
+/// Returned when a size isn't a percentage like `65`/`65%` or a number of
+/// cells like `80c`, or is out of range.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ParseSizeError {
+    pub value: String,
+}
+
+impl fmt::Display for ParseSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid size `{}`, expected a percentage from 1 to 99 like `65%` or a number of cells like `80c`",
+            self.value
+        )
+    }
+}
+
+impl Error for ParseSizeError {}
+
+impl FromStr for Size {
+    type Err = ParseSizeError;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let error = || ParseSizeError {
+            value: String::from(value),
+        };
+        let trimmed = value.trim();
+
+        match trimmed.strip_suffix('c') {
+            Some(cells) => match cells.trim_end().parse() {
+                Ok(cells) if cells > 0 => Ok(Size::Cells(cells)),
+                _ => Err(error()),
+            },
+            None => match trimmed.trim_end_matches('%').trim_end().parse() {
+                Ok(percent @ 1..=99) => Ok(Size::Percent(percent)),
+                _ => Err(error()),
+            },
+        }
+    }
+}
+
 // TODO: Refactor to use more efficient
 // data types
 #[derive(Debug, Clone)]
@@ -19,50 +120,45 @@ pub struct Pane {
 }
 
 impl Pane {
-    pub fn new(cwd: Option<&str>) -> Pane {
-        let id = match CLI::spawn(cwd) {
-            Ok(id) => id,
-            Err(e) => panic!("Failed to split pane: {}", e),
-        };
+    pub fn new(backend: &dyn Multiplexer, cwd: Option<&str>) -> Result<Pane> {
+        let id = backend.spawn(cwd)?;
 
-        Pane {
+        Ok(Pane {
             id,
             parent_id: None,
-        }
+        })
     }
 
     pub fn split(
         &self,
+        backend: &dyn Multiplexer,
         direction: &SplitDirection,
-        percentage: Option<&str>,
+        size: Option<Size>,
         parent: Option<&Pane>,
         top_level: bool,
-    ) -> Pane {
+    ) -> Result<Pane> {
         let pane_to_split = match parent {
             Some(pane) => pane.id.clone(),
             None => self.id.clone(),
         };
 
-        let id = match CLI::split_pane(&pane_to_split, direction, percentage, top_level) {
-            Ok(id) => id,
-            Err(e) => panic!("Failed to split pane: {}", e),
-        };
+        let id = backend.split_pane(&pane_to_split, direction, size, top_level, None)?;
 
-        Pane {
+        Ok(Pane {
             id,
             parent_id: Some(pane_to_split),
-        }
+        })
     }
 
-    pub fn set_tab_title(&self, title: &str) -> Result<(), Box<dyn Error>> {
-        CLI::set_tab_title(&self.id, title)
+    pub fn set_tab_title(&self, backend: &dyn Multiplexer, title: &str) -> Result<()> {
+        backend.set_tab_title(&self.id, title)
     }
 
-    pub fn focus(&self) -> Result<(), Box<dyn Error>> {
-        CLI::focus(&self.id)
+    pub fn focus(&self, backend: &dyn Multiplexer) -> Result<()> {
+        backend.focus(&self.id)
     }
 
-    pub fn run_command(&self, command: &str) {
-        CLI::run_command(&self.id, command)
+    pub fn run_command(&self, backend: &dyn Multiplexer, command: &str) -> Result<()> {
+        backend.send_text(&self.id, &format!("{}\n", command))
     }
 }
@@ -0,0 +1,72 @@
+use weztermocil::diagnostics::{Diagnostic, Location, YamlLocator};
+
+const LAYOUT: &str = "\
+name: sample
+# windows are tabs
+windows:
+  - name: editor
+    layout: main-vertical
+    panes:
+      - vim
+      - commands:
+          - git status
+        focus: true
+  - name: servers
+    panes: [rails s, yarn dev]
+- name: ignored
+";
+
+fn at(line: usize, column: usize) -> Option<Location> {
+    Some(Location { line, column })
+}
+
+#[test]
+fn locates_block_nodes() {
+    let locator = YamlLocator::new(LAYOUT);
+
+    assert_eq!(locator.locate("name"), at(1, 1));
+    assert_eq!(locator.locate("windows"), at(3, 1));
+    assert_eq!(locator.locate("windows[0]"), at(4, 3));
+    assert_eq!(locator.locate("windows[0].layout"), at(5, 5));
+    assert_eq!(locator.locate("windows[0].panes[1].commands[0]"), at(9, 11));
+    assert_eq!(locator.locate("windows[0].panes[1].focus"), at(10, 9));
+    assert_eq!(locator.locate("windows[1].name"), at(11, 5));
+}
+
+#[test]
+fn falls_back_to_the_closest_ancestor() {
+    let locator = YamlLocator::new(LAYOUT);
+
+    assert_eq!(locator.locate("windows[1].panes[1]"), at(12, 5));
+    assert_eq!(locator.locate("windows[0].root"), at(4, 3));
+    assert_eq!(locator.locate("nope"), None);
+}
+
+#[test]
+fn skips_block_scalars() {
+    let locator = YamlLocator::new(
+        "windows:\n  - diagram: |\n      .---.\n      |(0)|\n      '---'\n    panes: [vim]\n",
+    );
+
+    assert_eq!(locator.locate("windows[0].diagram"), at(2, 5));
+    assert_eq!(locator.locate("windows[0].panes"), at(6, 5));
+}
+
+#[test]
+fn sequences_can_share_their_key_indentation() {
+    let locator = YamlLocator::new("windows:\n- name: a\n- name: b\n  panes:\n  - x\n");
+
+    assert_eq!(locator.locate("windows[1].name"), at(3, 3));
+    assert_eq!(locator.locate("windows[1].panes[0]"), at(5, 3));
+}
+
+#[test]
+fn renders_diagnostics_with_their_location() {
+    let mut diagnostics = vec![Diagnostic::error("windows[0].layout", "unknown layout")];
+    YamlLocator::new(LAYOUT).annotate(&mut diagnostics);
+
+    assert_eq!(
+        diagnostics[0].render("dev.yml"),
+        "dev.yml:5:5: error: windows[0].layout: unknown layout"
+    );
+}
@@ -0,0 +1,89 @@
+use weztermocil::{
+    geometry::{Rect, SplitError, Tab},
+    wezterm::pane::{Size, SplitDirection},
+};
+
+fn at(left: usize, top: usize, width: usize, height: usize) -> Rect {
+    Rect {
+        left,
+        top,
+        width,
+        height,
+    }
+}
+
+#[test]
+fn gives_the_new_pane_its_size_and_the_divider_to_the_old_one() {
+    let mut tab = Tab::new("0", 120, 40);
+    tab.split("0", &SplitDirection::Right, None, false, "1")
+        .unwrap();
+    tab.split("1", &SplitDirection::Top, Some(Size::Cells(10)), false, "2")
+        .unwrap();
+    tab.split(
+        "0",
+        &SplitDirection::Left,
+        Some(Size::Percent(33)),
+        false,
+        "3",
+    )
+    .unwrap();
+
+    assert_eq!(
+        tab.rects(),
+        vec![
+            (String::from("3"), at(0, 0, 19, 40)),
+            (String::from("0"), at(20, 0, 39, 40)),
+            (String::from("2"), at(60, 0, 60, 10)),
+            (String::from("1"), at(60, 11, 60, 29)),
+        ]
+    );
+}
+
+#[test]
+fn fits_the_tab_into_what_a_top_level_split_leaves() {
+    let mut tab = Tab::new("0", 120, 40);
+    tab.split("0", &SplitDirection::Right, None, false, "1")
+        .unwrap();
+    tab.split("1", &SplitDirection::Bottom, None, false, "2")
+        .unwrap();
+    tab.split(
+        "1",
+        &SplitDirection::Bottom,
+        Some(Size::Percent(25)),
+        true,
+        "3",
+    )
+    .unwrap();
+
+    assert_eq!(
+        tab.rects(),
+        vec![
+            (String::from("0"), at(0, 0, 59, 29)),
+            (String::from("1"), at(60, 0, 60, 13)),
+            (String::from("2"), at(60, 14, 60, 15)),
+            (String::from("3"), at(0, 30, 120, 10)),
+        ]
+    );
+}
+
+#[test]
+fn refuses_splits_that_do_not_fit() {
+    let mut tab = Tab::new("0", 3, 1);
+
+    assert_eq!(
+        tab.split("0", &SplitDirection::Right, None, false, "1"),
+        Ok(())
+    );
+    assert_eq!(
+        tab.split("0", &SplitDirection::Right, None, false, "2"),
+        Err(SplitError::NoRoom {
+            pane_id: String::from("0"),
+            direction: SplitDirection::Right,
+            size: Size::Percent(50),
+        })
+    );
+    assert_eq!(
+        tab.split("9", &SplitDirection::Right, None, false, "2"),
+        Err(SplitError::UnknownPane(String::from("9")))
+    );
+}
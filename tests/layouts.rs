@@ -0,0 +1,696 @@
+//! Golden tests for the splits each `Layout` issues.
+//!
+//! Every layout is run against a `RecordingBackend` for 0 to 12 panes and the
+//! resulting split calls and pane order are compared against the files in
+//! `tests/golden`. Run with `UPDATE_GOLDEN=1` to regenerate them after an
+//! intentional change, and review the diff before committing.
+
+use std::{env, fmt::Write, fs, path::PathBuf};
+
+use weztermocil::{
+    config::{WindowConfig, YAMLConfig},
+    layout::{tiled_rows, Diagram, Layout, LayoutOptions, SplitTree, TotalPanes},
+    multiplexer::{RecordingBackend, TrackingBackend},
+    wezterm::pane::{Pane, Size, SplitDirection},
+};
+
+const MAX_PANES: usize = 12;
+
+fn record(layout: &Layout, total_panes: usize) -> String {
+    let backend = RecordingBackend::new();
+    let starting_pane = Pane::new(&backend, None).unwrap();
+    let panes = layout
+        .create(
+            &backend,
+            TotalPanes(total_panes),
+            starting_pane,
+            &LayoutOptions::default(),
+        )
+        .unwrap();
+
+    let mut out = String::new();
+    writeln!(out, "== {} panes ==", total_panes).unwrap();
+    for call in backend.splits() {
+        writeln!(out, "{}", call).unwrap();
+    }
+    let ids = panes.iter().map(|p| p.id.as_str()).collect::<Vec<_>>();
+    writeln!(out, "panes: [{}]", ids.join(", ")).unwrap();
+
+    out
+}
+
+fn assert_golden(name: &str, layout: Layout) {
+    let actual = (0..=MAX_PANES)
+        .map(|n| record(&layout, n))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.txt", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Missing golden file {}", path.display()));
+    assert_eq!(
+        expected,
+        actual,
+        "{} no longer matches {}",
+        name,
+        path.display()
+    );
+}
+
+#[test]
+fn even_horizontal() {
+    assert_golden("even-horizontal", Layout::EvenHorizontal);
+}
+
+#[test]
+fn even_vertical() {
+    assert_golden("even-vertical", Layout::EvenVertical);
+}
+
+#[test]
+fn main_vertical() {
+    assert_golden("main-vertical", Layout::MainVertical);
+}
+
+#[test]
+fn main_vertical_flipped() {
+    assert_golden("main-vertical-flipped", Layout::MainVerticalFlipped);
+}
+
+#[test]
+fn main_horizontal() {
+    assert_golden("main-horizontal", Layout::MainHorizontal);
+}
+
+#[test]
+fn main_horizontal_flipped() {
+    assert_golden("main-horizontal-flipped", Layout::MainHorizontalFlipped);
+}
+
+#[test]
+fn tiled() {
+    assert_golden("tiled", Layout::Tiled);
+}
+
+#[test]
+fn three_columns() {
+    assert_golden("3_columns", Layout::ThreeColumns);
+}
+
+#[test]
+fn double_main_horizontal() {
+    assert_golden("double-main-horizontal", Layout::DoubleMainHorizontal);
+}
+
+#[test]
+fn double_main_vertical() {
+    assert_golden("double-main-vertical", Layout::DoubleMainVertical);
+}
+
+#[test]
+fn columns() {
+    assert_golden("columns-4", Layout::Columns(4));
+}
+
+#[test]
+fn rows() {
+    assert_golden("rows-3", Layout::Rows(3));
+}
+
+#[test]
+fn spiral() {
+    assert_golden("spiral", Layout::Spiral);
+}
+
+#[test]
+fn centered_main() {
+    assert_golden("centered-main", Layout::CenteredMain);
+}
+
+#[test]
+fn parses_every_layout_name() {
+    for name in Layout::NAMES {
+        let name = name.replace('N', "4");
+        let layout = name.parse::<Layout>();
+        assert!(layout.is_ok(), "{} should parse", name);
+        assert_eq!(layout.unwrap().to_string(), name);
+    }
+    assert!(Layout::NAMES
+        .iter()
+        .all(|name| Layout::describe(name).is_some()));
+
+    for invalid in ["columns", "columns:0", "rows:two", "columns:4:2"] {
+        assert!(
+            invalid.parse::<Layout>().is_err(),
+            "{} should be rejected",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn suggests_close_layout_names() {
+    let error = "main_vertical".parse::<Layout>().unwrap_err();
+    assert_eq!(error.suggestion, Some("main-vertical"));
+
+    let error = "spiral-galaxy".parse::<Layout>().unwrap_err();
+    assert_eq!(error.suggestion, None);
+}
+
+fn record_with(layout: Layout, total_panes: usize, options: LayoutOptions) -> Vec<String> {
+    let backend = RecordingBackend::new();
+    let starting_pane = Pane::new(&backend, None).unwrap();
+    let panes = layout
+        .create(&backend, TotalPanes(total_panes), starting_pane, &options)
+        .unwrap();
+
+    let mut calls = backend
+        .splits()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let ids = panes.iter().map(|p| p.id.as_str()).collect::<Vec<_>>();
+    calls.push(format!("panes: [{}]", ids.join(", ")));
+    calls
+}
+
+/// Where each pane `layout` returns ends up on a 120 by 40 tab, as
+/// `(left, top, width, height)`.
+fn rects(
+    layout: Layout,
+    total_panes: usize,
+    options: LayoutOptions,
+) -> Vec<(usize, usize, usize, usize)> {
+    let recording = RecordingBackend::new();
+    let backend = TrackingBackend::new(&recording, 120, 40);
+    let starting_pane = Pane::new(&backend, None).unwrap();
+    let panes = layout
+        .create(&backend, TotalPanes(total_panes), starting_pane, &options)
+        .unwrap();
+
+    panes
+        .iter()
+        .map(|pane| {
+            let rect = backend.rect(&pane.id).unwrap();
+            (rect.left, rect.top, rect.width, rect.height)
+        })
+        .collect()
+}
+
+#[test]
+fn parses_sizes() {
+    assert_eq!("65".parse(), Ok(Size::Percent(65)));
+    assert_eq!("65%".parse(), Ok(Size::Percent(65)));
+    assert_eq!("80c".parse(), Ok(Size::Cells(80)));
+
+    for invalid in ["0", "100%", "0c", "-5", "65px", ""] {
+        assert!(
+            invalid.parse::<Size>().is_err(),
+            "{} should be rejected",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn sizes_the_main_pane() {
+    let percent = LayoutOptions {
+        main_size: Some(Size::Percent(65)),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        record_with(Layout::MainVertical, 3, percent),
+        vec![
+            "split 0 right 35% => 1",
+            "split 1 bottom 50% => 2",
+            "panes: [0, 1, 2]"
+        ]
+    );
+
+    // With cells the main pane is split off the other side, so it's
+    // the pane that gets the exact size.
+    let cells = LayoutOptions {
+        main_size: Some(Size::Cells(120)),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        record_with(Layout::MainHorizontal, 3, cells),
+        vec![
+            "split 0 top 120c => 1",
+            "split 0 right 50% => 2",
+            "panes: [1, 0, 2]"
+        ]
+    );
+}
+
+#[test]
+fn sizes_columns() {
+    let options = LayoutOptions {
+        column_sizes: Some(vec![
+            Size::Percent(20),
+            Size::Percent(50),
+            Size::Percent(30),
+        ]),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        record_with(Layout::ThreeColumns, 3, options),
+        vec![
+            "split 0 right 30% => 1",
+            "split 0 right 71% => 2",
+            "panes: [0, 2, 1]"
+        ]
+    );
+
+    let options = LayoutOptions {
+        main_size: Some(Size::Cells(80)),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        record_with(Layout::DoubleMainVertical, 4, options),
+        vec![
+            "split 0 left 80c => 1",
+            "split 0 left 80c => 2",
+            "split 0 bottom 50% => 3",
+            "panes: [1, 2, 0, 3]"
+        ]
+    );
+}
+
+#[test]
+fn rejects_options_that_do_not_fit_the_layout() {
+    let main_size = LayoutOptions {
+        main_size: Some(Size::Percent(60)),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        Layout::Tiled.check_options(&main_size).unwrap_err().option,
+        "main_size"
+    );
+    assert!(Layout::DoubleMainVertical
+        .check_options(&main_size)
+        .is_err());
+    assert!(Layout::MainVerticalFlipped
+        .check_options(&main_size)
+        .is_ok());
+
+    // Two main columns fit as long as they leave room for the last one,
+    // and whatever passes the check can be laid out.
+    for percent in 1..=49 {
+        let options = LayoutOptions {
+            main_size: Some(Size::Percent(percent)),
+            ..LayoutOptions::default()
+        };
+        assert!(Layout::DoubleMainVertical.check_options(&options).is_ok());
+        assert_eq!(rects(Layout::DoubleMainVertical, 4, options).len(), 4);
+    }
+    for percent in [50, 51, 99] {
+        let options = LayoutOptions {
+            main_size: Some(Size::Percent(percent)),
+            ..LayoutOptions::default()
+        };
+        assert!(Layout::DoubleMainVertical.check_options(&options).is_err());
+    }
+
+    for sizes in [
+        vec![Size::Percent(50), Size::Percent(50)],
+        vec![Size::Percent(50), Size::Percent(40), Size::Percent(30)],
+    ] {
+        let options = LayoutOptions {
+            column_sizes: Some(sizes.clone()),
+            ..LayoutOptions::default()
+        };
+        assert!(
+            Layout::ThreeColumns.check_options(&options).is_err(),
+            "{:?} should be rejected",
+            sizes
+        );
+    }
+}
+
+#[test]
+fn creates_custom_trees_in_leaf_order() {
+    let pane = |size| SplitTree::Pane { size };
+    let tree = SplitTree::Split {
+        size: None,
+        direction: SplitDirection::Right,
+        children: vec![
+            pane(Some(Size::Percent(60))),
+            SplitTree::Split {
+                size: None,
+                direction: SplitDirection::Bottom,
+                children: vec![
+                    pane(Some(Size::Percent(70))),
+                    SplitTree::Split {
+                        size: None,
+                        direction: SplitDirection::Right,
+                        children: vec![pane(None), pane(None), pane(None)],
+                    },
+                ],
+            },
+        ],
+    };
+    let options = LayoutOptions {
+        tree: Some(tree),
+        ..LayoutOptions::default()
+    };
+
+    assert_eq!(
+        record_with(Layout::Custom, 5, options),
+        vec![
+            "split 0 right 40% => 1",
+            "split 1 bottom 30% => 2",
+            "split 2 right 33% => 3",
+            "split 2 right 49% => 4",
+            "panes: [0, 1, 2, 4, 3]"
+        ]
+    );
+}
+
+#[test]
+fn checks_custom_trees() {
+    let options = |tree| LayoutOptions {
+        tree,
+        ..LayoutOptions::default()
+    };
+    let split = |children| SplitTree::Split {
+        size: None,
+        direction: SplitDirection::Bottom,
+        children,
+    };
+
+    assert!(Layout::Custom.check_options(&options(None)).is_err());
+    assert!(Layout::Tiled
+        .check_options(&options(Some(SplitTree::Pane { size: None })))
+        .is_err());
+
+    let error = Layout::Custom
+        .check_options(&options(Some(split(vec![
+            SplitTree::Pane { size: None },
+            split(vec![]),
+        ]))))
+        .unwrap_err();
+    assert_eq!(error.option, "tree.children[1].children");
+
+    let error = Layout::Custom
+        .check_options(&options(Some(split(vec![
+            SplitTree::Pane {
+                size: Some(Size::Percent(60)),
+            },
+            SplitTree::Pane {
+                size: Some(Size::Percent(50)),
+            },
+        ]))))
+        .unwrap_err();
+    assert_eq!(error.option, "tree.children");
+}
+
+#[test]
+fn parses_diagrams_into_trees() {
+    let diagram: Diagram = "
+.------------------.------------------.
+| (0)              | (2)              |
+|                  |                  |
+|                  |                  |
+|                  |------------------|
+| vim              | (1)              |
+'------------------'------------------'
+"
+    .parse()
+    .unwrap();
+
+    assert_eq!(
+        diagram.tree,
+        SplitTree::Split {
+            size: None,
+            direction: SplitDirection::Right,
+            children: vec![
+                SplitTree::Pane {
+                    size: Some(Size::Percent(50))
+                },
+                SplitTree::Split {
+                    size: Some(Size::Percent(50)),
+                    direction: SplitDirection::Bottom,
+                    children: vec![
+                        SplitTree::Pane {
+                            size: Some(Size::Percent(75))
+                        },
+                        SplitTree::Pane {
+                            size: Some(Size::Percent(25))
+                        },
+                    ],
+                },
+            ],
+        }
+    );
+    assert_eq!(diagram.labels, vec![0, 2, 1]);
+}
+
+#[test]
+fn points_at_mistakes_in_diagrams() {
+    let error = |source: &str| {
+        let error = source.parse::<Diagram>().unwrap_err();
+        (error.line, error.column)
+    };
+
+    // Numbered twice.
+    assert_eq!(error(".-----.-----.\n| (0) | (0) |\n'-----'-----'"), (2, 9));
+    // Without a number.
+    assert_eq!(error(".-----.-----.\n| (0) |     |\n'-----'-----'"), (2, 8));
+    // A line that stops halfway.
+    assert_eq!(
+        error(".-----.-----.\n| (0) | (1) |\n|     '-----|\n| (2)       |\n'-----------'"),
+        (2, 7)
+    );
+    // An open outline.
+    assert_eq!(error(".-----.\n| (0) |\n'-----"), (3, 7));
+}
+
+#[test]
+fn orders_columns_by_where_they_end_up() {
+    assert_eq!(
+        rects(Layout::ThreeColumns, 3, LayoutOptions::default()),
+        vec![(0, 0, 40, 40), (41, 0, 39, 40), (81, 0, 39, 40)]
+    );
+    assert_eq!(
+        rects(Layout::DoubleMainVertical, 4, LayoutOptions::default()),
+        vec![
+            (0, 0, 40, 40),
+            (41, 0, 39, 40),
+            (81, 0, 39, 20),
+            (81, 21, 39, 19)
+        ]
+    );
+    assert_eq!(
+        rects(
+            Layout::DoubleMainVertical,
+            3,
+            LayoutOptions {
+                main_size: Some(Size::Cells(30)),
+                ..LayoutOptions::default()
+            }
+        ),
+        vec![(0, 0, 30, 40), (31, 0, 30, 40), (62, 0, 58, 40)]
+    );
+}
+
+#[test]
+fn returns_panes_in_reading_order() {
+    let is_sorted_by = |rects: &[(usize, usize, usize, usize)], rows: bool| {
+        let keys = rects
+            .iter()
+            .map(|&(left, top, ..)| if rows { (top, left) } else { (left, top) })
+            .collect::<Vec<_>>();
+        keys.windows(2).all(|pair| pair[0] < pair[1])
+    };
+
+    for total_panes in 2..=MAX_PANES {
+        for layout in [
+            Layout::EvenHorizontal,
+            Layout::ThreeColumns,
+            Layout::DoubleMainVertical,
+            Layout::Columns(4),
+        ] {
+            let rects = rects(layout, total_panes, LayoutOptions::default());
+            assert!(is_sorted_by(&rects, false), "{:?}: {:?}", layout, rects);
+        }
+
+        for layout in [
+            Layout::EvenVertical,
+            Layout::Tiled,
+            Layout::DoubleMainHorizontal,
+            Layout::Rows(3),
+        ] {
+            let rects = rects(layout, total_panes, LayoutOptions::default());
+            assert!(is_sorted_by(&rects, true), "{:?}: {:?}", layout, rects);
+        }
+
+        for (layout, main) in [
+            (Layout::MainVertical, (0, 0, 59, 40)),
+            (Layout::MainVerticalFlipped, (61, 0, 59, 40)),
+            (Layout::MainHorizontal, (0, 0, 120, 19)),
+            (Layout::MainHorizontalFlipped, (0, 21, 120, 19)),
+        ] {
+            let rects = rects(layout, total_panes, LayoutOptions::default());
+            assert_eq!(rects[0], main, "{:?}", layout);
+            assert!(is_sorted_by(&rects[1..], true), "{:?}: {:?}", layout, rects);
+        }
+    }
+}
+
+#[test]
+fn tiles_panes_in_a_near_square_grid() {
+    let shapes = (1..=12)
+        .map(|total_panes| tiled_rows(total_panes, None, None))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        shapes,
+        vec![
+            vec![1],
+            vec![2],
+            vec![2, 1],
+            vec![2, 2],
+            vec![3, 2],
+            vec![3, 3],
+            vec![3, 2, 2],
+            vec![3, 3, 2],
+            vec![3, 3, 3],
+            vec![4, 3, 3],
+            vec![4, 4, 3],
+            vec![4, 4, 4],
+        ]
+    );
+
+    assert_eq!(
+        rects(Layout::Tiled, 5, LayoutOptions::default()),
+        vec![
+            (0, 0, 40, 20),
+            (41, 0, 39, 20),
+            (81, 0, 39, 20),
+            (0, 21, 60, 19),
+            (61, 21, 59, 19)
+        ]
+    );
+}
+
+#[test]
+fn honours_tiled_columns_and_rows() {
+    assert_eq!(tiled_rows(7, Some(2), None), vec![2, 2, 2, 1]);
+    assert_eq!(tiled_rows(7, None, Some(2)), vec![4, 3]);
+    assert_eq!(tiled_rows(5, Some(4), None), vec![3, 2]);
+    assert_eq!(tiled_rows(4, Some(4), Some(2)), vec![2, 2]);
+    // A grid too small for every pane gets more rows.
+    assert_eq!(tiled_rows(5, Some(2), Some(2)), vec![2, 2, 1]);
+    // There's never more than a row per pane.
+    assert_eq!(tiled_rows(2, None, Some(5)), vec![1, 1]);
+
+    let columns = LayoutOptions {
+        columns: Some(1),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        rects(Layout::Tiled, 3, columns.clone()),
+        vec![(0, 0, 120, 13), (0, 14, 120, 13), (0, 28, 120, 12)]
+    );
+    assert_eq!(
+        Layout::MainVertical
+            .check_options(&columns)
+            .unwrap_err()
+            .option,
+        "columns"
+    );
+    let no_rows = LayoutOptions {
+        rows: Some(0),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        Layout::Tiled.check_options(&no_rows).unwrap_err().option,
+        "rows"
+    );
+}
+
+#[test]
+fn splits_evenly_in_cells_when_the_size_is_known() {
+    let widths = rects(Layout::EvenHorizontal, 7, LayoutOptions::default())
+        .into_iter()
+        .map(|(_, _, width, _)| width)
+        .collect::<Vec<_>>();
+    assert_eq!(widths, vec![17, 17, 16, 16, 16, 16, 16]);
+
+    // Cells and percentages can be mixed, and children without a size
+    // share what's left.
+    let pane = |size| SplitTree::Pane { size };
+    let options = LayoutOptions {
+        tree: Some(SplitTree::Split {
+            size: None,
+            direction: SplitDirection::Right,
+            children: vec![
+                pane(Some(Size::Cells(80))),
+                pane(None),
+                pane(Some(Size::Percent(20))),
+            ],
+        }),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        rects(Layout::Custom, 3, options.clone()),
+        vec![(0, 0, 80, 40), (81, 0, 15, 40), (97, 0, 23, 40)]
+    );
+
+    // Only the two main columns are sized, the last one takes the rest.
+    let main_size = LayoutOptions {
+        main_size: Some(Size::Percent(40)),
+        ..LayoutOptions::default()
+    };
+    assert_eq!(
+        rects(Layout::DoubleMainVertical, 4, main_size),
+        vec![
+            (0, 0, 47, 40),
+            (48, 0, 47, 40),
+            (96, 0, 24, 20),
+            (96, 21, 24, 19)
+        ]
+    );
+
+    // Without the size, there's nothing to work the cells out from.
+    let backend = RecordingBackend::new();
+    let starting_pane = Pane::new(&backend, None).unwrap();
+    assert!(Layout::Custom
+        .create(&backend, TotalPanes(3), starting_pane, &options)
+        .is_err());
+}
+
+#[test]
+fn fits_as_many_spiral_panes_as_the_tab_has_room_for() {
+    let spiral = |columns: usize, rows: usize, panes: usize| {
+        let recording = RecordingBackend::new();
+        let backend = TrackingBackend::new(&recording, columns, rows);
+        let starting_pane = Pane::new(&backend, None).unwrap();
+        Layout::Spiral
+            .create(
+                &backend,
+                TotalPanes(panes),
+                starting_pane,
+                &LayoutOptions::default(),
+            )
+            .map(|panes| panes.len())
+    };
+
+    assert_eq!(spiral(120, 40, 10).unwrap(), 10);
+    assert!(spiral(120, 40, 11).is_err());
+    assert_eq!(spiral(400, 100, 12).unwrap(), 12);
+
+    // How many fit depends on the tab, so the config check leaves it to the splits.
+    let window = (0..12).fold(WindowConfig::new().layout("spiral"), |window, n| {
+        window.pane(format!("echo {}", n).as_str())
+    });
+    assert_eq!(YAMLConfig::new().window(window).check(), vec![]);
+}
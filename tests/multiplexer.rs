@@ -0,0 +1,256 @@
+use std::cell::Cell;
+
+use weztermocil::{
+    config::{WindowConfig, YAMLConfig},
+    error::{Result, WeztermocilError},
+    multiplexer::{Call, Multiplexer, RecordingBackend, RollbackBackend},
+    wezterm::{
+        pane::{Pane, PaneSize, Size, SplitDirection},
+        DryRunBackend,
+    },
+    Session,
+};
+
+/// Records every call like a [`RecordingBackend`], but fails the
+/// `fail_at`th split, counting from 1.
+struct FailingBackend {
+    recording: RecordingBackend,
+    fail_at: usize,
+    splits: Cell<usize>,
+}
+
+impl FailingBackend {
+    fn new(fail_at: usize) -> FailingBackend {
+        FailingBackend {
+            recording: RecordingBackend::new(),
+            fail_at,
+            splits: Cell::new(0),
+        }
+    }
+
+    fn killed(&self) -> Vec<String> {
+        self.recording
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                Call::KillPane { pane_id } => Some(pane_id),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Multiplexer for FailingBackend {
+    fn spawn(&self, cwd: Option<&str>) -> Result<String> {
+        self.recording.spawn(cwd)
+    }
+
+    fn split_pane(
+        &self,
+        pane_id: &str,
+        direction: &SplitDirection,
+        size: Option<Size>,
+        top_level: bool,
+        cwd: Option<&str>,
+    ) -> Result<String> {
+        self.splits.set(self.splits.get() + 1);
+        if self.splits.get() == self.fail_at {
+            return Err(WeztermocilError::CommandFailed {
+                command: String::from("wezterm cli split-pane"),
+                status: Some(1),
+                stderr: String::new(),
+            });
+        }
+
+        self.recording
+            .split_pane(pane_id, direction, size, top_level, cwd)
+    }
+
+    fn pane_size(&self, pane_id: &str) -> Result<Option<PaneSize>> {
+        self.recording.pane_size(pane_id)
+    }
+
+    fn set_tab_title(&self, pane_id: &str, title: &str) -> Result<()> {
+        self.recording.set_tab_title(pane_id, title)
+    }
+
+    fn focus(&self, pane_id: &str) -> Result<()> {
+        self.recording.focus(pane_id)
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.recording.send_text(pane_id, text)
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.recording.kill_pane(pane_id)
+    }
+}
+
+#[test]
+fn rollback_kills_created_panes_newest_first() {
+    let recording = RecordingBackend::new();
+    let existing = Pane::new(&recording, None).unwrap();
+
+    let rollback = RollbackBackend::new(&recording);
+    let tab = Pane::new(&rollback, None).unwrap();
+    tab.split(
+        &rollback,
+        &SplitDirection::Right,
+        Some(Size::Percent(50)),
+        None,
+        false,
+    )
+    .unwrap();
+    existing
+        .split(&rollback, &SplitDirection::Bottom, None, None, false)
+        .unwrap();
+
+    rollback.rollback().unwrap();
+
+    let killed = recording
+        .calls()
+        .into_iter()
+        .filter_map(|call| match call {
+            Call::KillPane { pane_id } => Some(pane_id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(killed, vec!["3", "2", "1"]);
+    assert!(rollback.created_panes().is_empty());
+}
+
+#[test]
+fn rollback_forgets_panes_killed_directly() {
+    let recording = RecordingBackend::new();
+    let rollback = RollbackBackend::new(&recording);
+    let tab = Pane::new(&rollback, None).unwrap();
+
+    rollback.kill_pane(&tab.id).unwrap();
+
+    assert!(rollback.created_panes().is_empty());
+}
+
+#[test]
+fn closes_what_a_failed_launch_created() {
+    // The first window goes into the current pane, which must survive.
+    let config = YAMLConfig::new()
+        .window(
+            WindowConfig::new()
+                .layout("even-horizontal")
+                .pane("a")
+                .pane("b"),
+        )
+        .window(
+            WindowConfig::new()
+                .layout("even-horizontal")
+                .pane("c")
+                .pane("d")
+                .pane("e"),
+        );
+    let here = Pane {
+        id: String::from("42"),
+        parent_id: None,
+    };
+
+    // Splits 42 => 0, spawns 1, splits 1 => 2 and fails on the next split.
+    let backend = FailingBackend::new(3);
+    let error = Session::new(config.clone())
+        .here(here.clone())
+        .launch(&backend)
+        .unwrap_err();
+    assert_eq!(error.exit_code(), 5);
+    assert_eq!(backend.killed(), vec!["2", "1", "0"]);
+
+    let backend = FailingBackend::new(3);
+    Session::new(config)
+        .here(here)
+        .rollback(false)
+        .launch(&backend)
+        .unwrap_err();
+    assert!(backend.killed().is_empty());
+}
+
+#[test]
+fn dry_run_prints_the_calls_it_would_make() {
+    let dry_run = DryRunBackend::buffered();
+
+    let tab = Pane::new(&dry_run, Some("/tmp/my project")).unwrap();
+    let right = tab
+        .split(
+            &dry_run,
+            &SplitDirection::Right,
+            Some(Size::Cells(40)),
+            None,
+            false,
+        )
+        .unwrap();
+    let bottom = right
+        .split(
+            &dry_run,
+            &SplitDirection::Bottom,
+            Some(Size::Percent(30)),
+            None,
+            true,
+        )
+        .unwrap();
+    tab.set_tab_title(&dry_run, "editor").unwrap();
+    bottom.run_command(&dry_run, "git status").unwrap();
+    bottom.focus(&dry_run).unwrap();
+
+    // Ids count up from 1, the current pane being 0.
+    assert_eq!(tab.id, "1");
+    assert_eq!(
+        (right.id.as_str(), right.parent_id.as_deref()),
+        ("2", Some("1"))
+    );
+    assert_eq!(
+        (bottom.id.as_str(), bottom.parent_id.as_deref()),
+        ("3", Some("2"))
+    );
+    assert_eq!(
+        dry_run.lines(),
+        vec![
+            "wezterm cli spawn --cwd '/tmp/my project'  # => pane 1",
+            "wezterm cli split-pane --pane-id 1 --right --cells 40  # => pane 2 (parent 1)",
+            "wezterm cli split-pane --pane-id 2 --bottom --percent 30 --top-level  # => pane 3 (parent 2)",
+            "wezterm cli set-tab-title editor --pane-id 1",
+            "echo 'git status' | wezterm cli send-text --pane-id 3 --no-paste",
+            "wezterm cli activate-pane --pane-id 3",
+        ]
+    );
+    assert_eq!(
+        dry_run.pane_size("1").unwrap(),
+        Some(PaneSize {
+            columns: 120,
+            rows: 40
+        })
+    );
+}
+
+#[test]
+fn dry_run_launches_here_into_a_stand_in_pane() {
+    let config = YAMLConfig::new()
+        .window(WindowConfig::new().root("/").pane("a").pane("b"))
+        .window(WindowConfig::new().pane("c"));
+    let dry_run = DryRunBackend::buffered();
+
+    Session::new(config)
+        .here(dry_run.current_pane())
+        .launch(&dry_run)
+        .unwrap();
+
+    assert_eq!(
+        dry_run.lines(),
+        vec![
+            "echo 'cd /' | wezterm cli send-text --pane-id 0 --no-paste",
+            "echo clear | wezterm cli send-text --pane-id 0 --no-paste",
+            "wezterm cli split-pane --pane-id 0 --right --cells 59  # => pane 1 (parent 0)",
+            "echo a | wezterm cli send-text --pane-id 0 --no-paste",
+            "echo b | wezterm cli send-text --pane-id 1 --no-paste",
+            "wezterm cli spawn  # => pane 2",
+            "echo c | wezterm cli send-text --pane-id 2 --no-paste",
+            "wezterm cli activate-pane --pane-id 0",
+        ]
+    );
+}
@@ -0,0 +1,128 @@
+use weztermocil::{
+    config::{PaneConfigOptions, WindowConfig, YAMLConfig},
+    error::{Result, WeztermocilError},
+    layout::{Diagram, Layout, LayoutOptions, LayoutRegistry, LayoutStrategy, TotalPanes},
+    multiplexer::Multiplexer,
+    preview,
+    wezterm::pane::Pane,
+};
+
+#[test]
+fn draws_windows_like_the_readme() {
+    let config = YAMLConfig::new().window(
+        WindowConfig::new()
+            .name("editor")
+            .layout("main-vertical")
+            .pane("vim")
+            .pane(PaneConfigOptions::new().command("git status").focus())
+            .pane("rails server"),
+    );
+
+    assert_eq!(
+        preview::render(&config, 36, 7).unwrap(),
+        "\
+windows[0] editor: main-vertical
+.-----------------.------------------.
+| (0)             | (1) <focus here> |
+| vim             | git status       |
+|                 |                  |
+|                 |------------------|
+|                 | (2)              |
+|                 | rails server     |
+|                 |                  |
+'-----------------'------------------'
+"
+    );
+}
+
+#[test]
+fn draws_diagrams_that_parse_back_into_the_same_layout() {
+    let diagram = "
+.-------------------.----------.
+| (1)               | (2)      |
+|                   |          |
+|-------------------|          |
+| (0)               |----------|
+|                   | (3)      |
+'-------------------'----------'";
+    let config = YAMLConfig::new().window(
+        WindowConfig::new()
+            .diagram(diagram)
+            .pane("a")
+            .pane("b")
+            .pane("c")
+            .pane("d"),
+    );
+
+    let preview = preview::render(&config, 60, 20).unwrap();
+    let drawn = preview.split_once('\n').unwrap().1;
+
+    let original = diagram.parse::<Diagram>().unwrap();
+    let redrawn = drawn.parse::<Diagram>().unwrap();
+    assert_eq!(redrawn.labels, original.labels);
+}
+
+#[test]
+fn fails_when_a_layout_does_not_fit() {
+    let config = YAMLConfig::new().window(
+        WindowConfig::new()
+            .layout("even-horizontal")
+            .pane("a")
+            .pane("b")
+            .pane("c"),
+    );
+
+    assert!(preview::render(&config, 3, 3).is_err());
+}
+
+#[test]
+fn draws_layouts_from_a_registry() {
+    let config = YAMLConfig::new().window(WindowConfig::new().layout("pair").pane("a").pane("b"));
+    assert!(preview::render(&config, 40, 4).is_err());
+
+    let layouts = LayoutRegistry::new().register("pair", Layout::Columns(2));
+    assert_eq!(
+        preview::render_with(&config, &layouts, 40, 4).unwrap(),
+        "\
+windows[0]: pair
+.--------------------.-------------------.
+| (0) <focus here>   | (1)               |
+| a                  | b                 |
+|                    |                   |
+|                    |                   |
+'--------------------'-------------------'
+"
+    );
+}
+
+/// A layout that hands back a pane it never split off.
+struct Stray;
+
+impl LayoutStrategy for Stray {
+    fn create(
+        &self,
+        _backend: &dyn Multiplexer,
+        _total_panes: TotalPanes,
+        starting_pane: Pane,
+        _options: &LayoutOptions,
+    ) -> Result<Vec<Pane>> {
+        let stray = Pane {
+            id: String::from("99"),
+            parent_id: Some(starting_pane.id.clone()),
+        };
+        Ok(vec![starting_pane, stray])
+    }
+}
+
+#[test]
+fn fails_when_a_layout_returns_a_pane_it_did_not_create() {
+    let config = YAMLConfig::new().window(WindowConfig::new().layout("stray").pane("a").pane("b"));
+    let layouts = LayoutRegistry::new().register("stray", Stray);
+
+    match preview::render_with(&config, &layouts, 40, 4) {
+        Err(WeztermocilError::Config(message)) => {
+            assert_eq!(message, "`stray` returned pane 99, which it didn't create")
+        }
+        other => panic!("expected a Config error, got {:?}", other),
+    }
+}
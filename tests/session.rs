@@ -0,0 +1,700 @@
+use std::{cell::RefCell, rc::Rc};
+
+use weztermocil::{
+    config::{
+        BreakpointConfig, PaneConfig, PaneConfigOptions, PreConfig, SplitNodeConfig, WindowConfig,
+        YAMLConfig,
+    },
+    diagnostics::Severity,
+    error::{Result, WeztermocilError},
+    layout::{Layout, LayoutOptions, LayoutRegistry, LayoutStrategy, TotalPanes},
+    multiplexer::{Multiplexer, RecordingBackend, TrackingBackend},
+    wezterm::pane::{Pane, PaneSize, SplitDirection},
+    Session, SessionEvent,
+};
+
+#[test]
+fn builds_the_same_config_as_yaml() {
+    let yaml: YAMLConfig = "
+windows:
+  - name: editor
+    layout: main-vertical
+    panes:
+      - vim
+      - commands:
+          - git status
+        focus: true
+"
+    .parse()
+    .unwrap();
+
+    let built = YAMLConfig::new().window(
+        WindowConfig::new()
+            .name("editor")
+            .layout("main-vertical")
+            .pane("vim")
+            .pane(PaneConfigOptions::new().command("git status").focus()),
+    );
+
+    assert_eq!(yaml, built);
+    assert_eq!(
+        built.windows.unwrap()[0].panes.as_deref().unwrap()[0],
+        PaneConfig::Command(String::from("vim"))
+    );
+}
+
+#[test]
+fn launches_a_session_built_in_code() {
+    let config = YAMLConfig::new()
+        .window(WindowConfig::new().name("one").pane("top"))
+        .window(
+            WindowConfig::new()
+                .name("two")
+                .layout("even-horizontal")
+                .command("source .env")
+                .pane("left")
+                .pane(PaneConfigOptions::new().command("right").focus()),
+        );
+    let recording = RecordingBackend::new();
+
+    let windows = Session::new(config).launch(&recording).unwrap();
+
+    let ids = windows
+        .iter()
+        .map(|panes| panes.iter().map(|p| p.id.as_str()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![vec!["0"], vec!["1", "2"]]);
+
+    let calls = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            "spawn => 0",
+            "title 0 \"one\"",
+            "send 0 \"top\\n\"",
+            "spawn => 1",
+            "title 1 \"two\"",
+            "split 1 right 50% => 2",
+            "send 1 \"source .env\\n\"",
+            "send 1 \"left\\n\"",
+            "send 2 \"source .env\\n\"",
+            "send 2 \"right\\n\"",
+            "focus 2",
+        ]
+    );
+}
+
+#[test]
+fn rejects_invalid_configs_before_creating_panes() {
+    let config = YAMLConfig::new().window(WindowConfig::new().layout("tilde").pane("a"));
+    let recording = RecordingBackend::new();
+
+    let error = Session::new(config).launch(&recording).unwrap_err();
+
+    assert!(error.to_string().contains("windows[0].layout"));
+    assert!(recording.calls().is_empty());
+}
+
+#[test]
+fn builds_custom_trees() {
+    let yaml: YAMLConfig = "
+windows:
+  - layout: custom
+    tree:
+      split: right
+      children:
+        - commands: [vim]
+          size: 60%
+        - split: bottom
+          children:
+            - git status
+            - commands: [htop]
+              focus: true
+"
+    .parse()
+    .unwrap();
+
+    let built = YAMLConfig::new().window(
+        WindowConfig::new().tree(
+            SplitNodeConfig::new()
+                .split("right")
+                .child(SplitNodeConfig::new().command("vim").size("60%"))
+                .child(
+                    SplitNodeConfig::new()
+                        .split("bottom")
+                        .child("git status")
+                        .child(SplitNodeConfig::new().command("htop").focus()),
+                ),
+        ),
+    );
+    assert_eq!(yaml, built);
+    assert_eq!(yaml.check(), vec![]);
+
+    let recording = RecordingBackend::new();
+    let windows = Session::new(built).launch(&recording).unwrap();
+    assert_eq!(windows[0].len(), 3);
+
+    let sent = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .filter(|call| call.starts_with("send") || call.starts_with("focus"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sent,
+        vec![
+            "send 0 \"vim\\n\"",
+            "send 1 \"git status\\n\"",
+            "send 2 \"htop\\n\"",
+            "focus 2"
+        ]
+    );
+}
+
+#[test]
+fn sends_commands_to_the_numbered_cells_of_a_diagram() {
+    let config = YAMLConfig::new().window(
+        WindowConfig::new()
+            .diagram(
+                "
+.-----.-----.
+| (2) | (0) |
+|     |-----|
+|     | (1) |
+'-----'-----'",
+            )
+            .pane("zero")
+            .pane("one")
+            .pane("two"),
+    );
+    assert_eq!(config.check(), vec![]);
+
+    let recording = RecordingBackend::new();
+    Session::new(config).launch(&recording).unwrap();
+
+    let calls = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            "spawn => 0",
+            "split 0 right 50% => 1",
+            "split 1 bottom 50% => 2",
+            "send 0 \"two\\n\"",
+            "send 1 \"zero\\n\"",
+            "send 2 \"one\\n\"",
+            "focus 0",
+        ]
+    );
+}
+
+#[test]
+fn warns_when_a_tiled_grid_is_too_small() {
+    let yaml: YAMLConfig = "
+windows:
+  - columns: 2
+    rows: 1
+    panes: [a, b, c]
+"
+    .parse()
+    .unwrap();
+
+    let built = YAMLConfig::new().window(
+        WindowConfig::new()
+            .columns(2)
+            .rows(1)
+            .pane("a")
+            .pane("b")
+            .pane("c"),
+    );
+    assert_eq!(yaml, built);
+
+    let diagnostics = built.check();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].path, "windows[0].rows");
+}
+
+#[test]
+fn picks_a_layout_by_the_size_of_the_window() {
+    let yaml: YAMLConfig = "
+windows:
+  - layout:
+      - min_cols: 200
+        layout: 3_columns
+      - min_cols: 100
+        min_rows: 30
+        layout: main-vertical
+      - layout: even-vertical
+    panes: [a, b, c]
+"
+    .parse()
+    .unwrap();
+
+    let built = YAMLConfig::new().window(
+        WindowConfig::new()
+            .breakpoint(BreakpointConfig::new("3_columns").min_cols(200))
+            .breakpoint(
+                BreakpointConfig::new("main-vertical")
+                    .min_cols(100)
+                    .min_rows(30),
+            )
+            .breakpoint(BreakpointConfig::new("even-vertical"))
+            .pane("a")
+            .pane("b")
+            .pane("c"),
+    );
+    assert_eq!(yaml, built);
+    assert_eq!(built.check(), vec![]);
+
+    let window = &built.windows.as_ref().unwrap()[0];
+    let size = |columns, rows| Some(PaneSize { columns, rows });
+    assert_eq!(
+        window.parsed_layout(size(240, 60)),
+        Ok(Layout::ThreeColumns)
+    );
+    assert_eq!(
+        window.parsed_layout(size(120, 40)),
+        Ok(Layout::MainVertical)
+    );
+    assert_eq!(
+        window.parsed_layout(size(120, 24)),
+        Ok(Layout::EvenVertical)
+    );
+    assert_eq!(window.parsed_layout(None), Ok(Layout::EvenVertical));
+
+    let recording = RecordingBackend::new();
+    let backend = TrackingBackend::new(&recording, 120, 40);
+    Session::new(built).launch(&backend).unwrap();
+
+    let splits = recording
+        .splits()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        splits,
+        vec!["split 0 right 50% => 1", "split 1 bottom 19c => 2"]
+    );
+
+    // A breakpoint without minimums always matches.
+    let shadowed = YAMLConfig::new().window(
+        WindowConfig::new()
+            .breakpoint(BreakpointConfig::new("tiled"))
+            .breakpoint(BreakpointConfig::new("main-vertical").min_cols(100)),
+    );
+    let diagnostics = shadowed.check();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].path, "windows[0].layout[1]");
+}
+
+/// Stacks every pane under the first one, each split off the last.
+struct Stack;
+
+impl LayoutStrategy for Stack {
+    fn create(
+        &self,
+        backend: &dyn Multiplexer,
+        total_panes: TotalPanes,
+        starting_pane: Pane,
+        _options: &LayoutOptions,
+    ) -> Result<Vec<Pane>> {
+        let mut panes = vec![starting_pane];
+        for _ in 1..total_panes.0 {
+            let last = panes.last().unwrap();
+            panes.push(last.split(backend, &SplitDirection::Bottom, None, None, false)?);
+        }
+        Ok(panes)
+    }
+}
+
+#[test]
+fn launches_layouts_from_a_registry() {
+    let layouts = LayoutRegistry::new()
+        .register("stack", Stack)
+        .register("pair", Layout::Columns(2));
+    assert_eq!(layouts.names(), vec!["pair", "stack"]);
+
+    let config = YAMLConfig::new()
+        .window(
+            WindowConfig::new()
+                .layout("stack")
+                .pane("a")
+                .pane("b")
+                .pane("c"),
+        )
+        .window(WindowConfig::new().layout("pair").pane("d").pane("e"));
+    assert!(config.check().iter().any(|d| d.path == "windows[0].layout"));
+    assert_eq!(config.check_with(&layouts), vec![]);
+
+    let recording = RecordingBackend::new();
+    Session::new(config)
+        .layouts(layouts.clone())
+        .launch(&recording)
+        .unwrap();
+    let splits = recording
+        .splits()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        splits,
+        vec![
+            "split 0 bottom => 1",
+            "split 1 bottom => 2",
+            "split 3 right 50% => 4"
+        ]
+    );
+
+    // Registered strategies don't take any options unless they say so.
+    let with_options = YAMLConfig::new().window(
+        WindowConfig::new()
+            .layout("stack")
+            .main_size("60%")
+            .pane("a"),
+    );
+    let diagnostics = with_options.check_with(&layouts);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "windows[0].main_size");
+}
+
+#[test]
+fn starts_panes_in_their_own_roots() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let yaml: YAMLConfig = format!(
+        "
+windows:
+  - root: {}
+    diagram: |
+      .-----.-----.
+      | (2) | (0) |
+      |     |-----|
+      |     | (1) |
+      '-----'-----'
+    panes:
+      - commands: [zero]
+        root: src
+      - one
+      - commands: [two]
+        root: tests/golden
+",
+        root
+    )
+    .parse()
+    .unwrap();
+
+    let built = YAMLConfig::new().window(
+        WindowConfig::new()
+            .root(root)
+            .diagram(
+                "
+.-----.-----.
+| (2) | (0) |
+|     |-----|
+|     | (1) |
+'-----'-----'
+",
+            )
+            .pane(PaneConfigOptions::new().command("zero").root("src"))
+            .pane("one")
+            .pane(PaneConfigOptions::new().command("two").root("tests/golden")),
+    );
+    assert_eq!(yaml.check(), vec![]);
+    assert_eq!(built.check(), vec![]);
+
+    let recording = RecordingBackend::new();
+    Session::new(built).launch(&recording).unwrap();
+
+    let calls = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .filter(|call| !call.starts_with("focus"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            format!("spawn --cwd {} => 0", root),
+            format!("send 0 \"cd {}/tests/golden\\n\"", root),
+            format!("split 0 right 50% --cwd {}/src => 1", root),
+            format!("split 1 bottom 50% --cwd {} => 2", root),
+            String::from("send 0 \"two\\n\""),
+            String::from("send 1 \"zero\\n\""),
+            String::from("send 2 \"one\\n\""),
+        ]
+    );
+
+    let missing = YAMLConfig::new().window(
+        WindowConfig::new()
+            .root(root)
+            .pane(PaneConfigOptions::new().root("no-such-dir")),
+    );
+    let diagnostics = missing.check();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "windows[0].panes[0].root");
+}
+
+#[test]
+fn resolves_roots_against_the_layout_file() {
+    let dir = std::env::temp_dir().join(format!("weztermocil-roots-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join(".weztermocil")).unwrap();
+    // Tests run in parallel, so only read variables that are always set.
+    let home = std::env::var("HOME").unwrap();
+
+    let path = dir.join(".weztermocil/dev.yml");
+    std::fs::write(
+        &path,
+        "
+windows:
+  - root: ./services/api
+    create_root: true
+    layout: even-horizontal
+    panes:
+      - server
+      - commands: [tail -f log]
+        root: logs
+      - commands: [ls]
+        root: ${HOME}
+",
+    )
+    .unwrap();
+
+    let session = Session::from_file(path.to_str().unwrap()).unwrap();
+    let mut without_create = session.config().clone();
+    without_create.windows.as_mut().unwrap()[0].create_root = false;
+    let paths = without_create
+        .check()
+        .into_iter()
+        .map(|diagnostic| diagnostic.path)
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["windows[0].root", "windows[0].panes[1].root"]);
+
+    let root = dir.join(".weztermocil/services/api");
+    let recording = RecordingBackend::new();
+    session.launch(&recording).unwrap();
+    assert!(root.join("logs").is_dir());
+
+    let calls = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .filter(|call| call.starts_with("spawn") || call.starts_with("split"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            format!("spawn --cwd {} => 0", root.display()),
+            format!("split 0 right 33% --cwd {} => 1", home),
+            format!("split 0 right 50% --cwd {}/logs => 2", root.display()),
+        ]
+    );
+
+    let variables = YAMLConfig::new()
+        .window(WindowConfig::new().root("$HOME"))
+        .window(WindowConfig::new().root("$WEZTERMOCIL_TEST_UNSET"));
+    let window = &variables.windows.as_ref().unwrap()[0];
+    assert_eq!(window.root_dir(None), Ok(Some(home.clone())));
+    let diagnostics = variables.check();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "windows[1].root");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn quotes_the_root_of_the_current_pane() {
+    let dir = std::env::temp_dir().join(format!("weztermocil here {}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let config =
+        YAMLConfig::new().window(WindowConfig::new().root(dir.to_str().unwrap()).pane("vim"));
+    let here = Pane {
+        id: String::from("42"),
+        parent_id: None,
+    };
+    let recording = RecordingBackend::new();
+    Session::new(config).here(here).launch(&recording).unwrap();
+
+    let calls = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls[0],
+        format!("send 42 {:?}", format!("cd '{}'\n", dir.display()))
+    );
+    assert!(calls.iter().all(|call| !call.starts_with("spawn")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reports_what_a_dry_run_would_do_instead_of_printing_it() {
+    let dir = std::env::temp_dir().join(format!("weztermocil-dry-run-{}", std::process::id()));
+    let config = YAMLConfig::new().window(
+        WindowConfig::new()
+            .root(dir.to_str().unwrap())
+            .create_root()
+            .pane("vim"),
+    );
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let sink = Rc::clone(&events);
+    let recording = RecordingBackend::new();
+    Session::new(config)
+        .dry_run(true)
+        .on_event(move |event| sink.borrow_mut().push(format!("{:?}", event)))
+        .launch(&recording)
+        .unwrap();
+
+    assert_eq!(
+        *events.borrow(),
+        vec![format!(
+            "WouldCreateRoot {{ dir: {:?} }}",
+            dir.to_str().unwrap()
+        )]
+    );
+    assert!(!dir.exists());
+}
+
+#[test]
+fn runs_pre_commands_in_order_before_any_pane() {
+    let single: YAMLConfig = "pre: echo one\nwindows: [{panes: [a]}]".parse().unwrap();
+    assert_eq!(
+        single.pre,
+        Some(PreConfig::Command(String::from("echo one")))
+    );
+    let list: YAMLConfig = "pre: [echo one, echo two]\nwindows: [{panes: [a]}]"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        list.pre.as_ref().unwrap().commands(),
+        vec!["echo one", "echo two"]
+    );
+    let built = YAMLConfig::new()
+        .pre("echo one")
+        .pre("echo two")
+        .window(WindowConfig::new().pane("a"));
+    assert_eq!(built.pre, list.pre);
+
+    let dir = std::env::temp_dir().join(format!("weztermocil-pre-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let log = dir.join("log");
+    let pre = |commands: &[&str]| {
+        commands.iter().fold(YAMLConfig::new(), |config, command| {
+            config.pre(&command.replace("LOG", log.to_str().unwrap()))
+        })
+    };
+
+    let recording = RecordingBackend::new();
+    let config = pre(&["echo one >> LOG", "echo two >> LOG"]).window(WindowConfig::new().pane("a"));
+    Session::new(config).launch(&recording).unwrap();
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "one\ntwo\n");
+    assert!(!recording.calls().is_empty());
+
+    // The first failure stops the launch before anything is spawned.
+    std::fs::remove_file(&log).unwrap();
+    let recording = RecordingBackend::new();
+    let config = pre(&["echo one >> LOG", "exit 3", "echo three >> LOG"])
+        .window(WindowConfig::new().pane("a"));
+    let error = Session::new(config).launch(&recording).unwrap_err();
+    assert!(matches!(
+        &error,
+        WeztermocilError::PreFailed { command, status: Some(3) } if command == "exit 3"
+    ));
+    assert_eq!(error.exit_code(), 7);
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "one\n");
+    assert!(recording.calls().is_empty());
+
+    // A dry run reports the commands instead of running them.
+    std::fs::remove_file(&log).unwrap();
+    let events = Rc::new(RefCell::new(vec![]));
+    let sink = Rc::clone(&events);
+    let config = pre(&["echo one >> LOG", "exit 3"]).window(WindowConfig::new().pane("a"));
+    Session::new(config)
+        .dry_run(true)
+        .on_event(move |event| {
+            if let SessionEvent::WouldRunPre { command } = event {
+                sink.borrow_mut().push(command.to_string());
+            }
+        })
+        .launch(&RecordingBackend::new())
+        .unwrap();
+    assert_eq!(
+        *events.borrow(),
+        vec![
+            format!("echo one >> {}", log.display()),
+            String::from("exit 3")
+        ]
+    );
+    assert!(!log.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn runs_window_commands_before_each_panes_own() {
+    let config: YAMLConfig = "
+windows:
+  - layout: even-horizontal
+    commands: [source .env, nvm use]
+    panes:
+      - commands: [npm test, npm run lint]
+      - npm start
+"
+    .parse()
+    .unwrap();
+    assert_eq!(
+        config.windows.as_ref().unwrap()[0].window_commands(),
+        vec!["source .env", "nvm use"]
+    );
+
+    let recording = RecordingBackend::new();
+    Session::new(config).launch(&recording).unwrap();
+
+    let sent = recording
+        .calls()
+        .iter()
+        .map(ToString::to_string)
+        .filter(|call| call.starts_with("send"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sent,
+        vec![
+            "send 0 \"source .env\\n\"",
+            "send 0 \"nvm use\\n\"",
+            "send 0 \"npm test\\n\"",
+            "send 0 \"npm run lint\\n\"",
+            "send 1 \"source .env\\n\"",
+            "send 1 \"nvm use\\n\"",
+            "send 1 \"npm start\\n\"",
+        ]
+    );
+
+    // `command` and `commands` can't both be set.
+    let both: YAMLConfig = "
+windows:
+  - command: source .env
+    commands: [nvm use]
+    panes: [npm start]
+"
+    .parse()
+    .unwrap();
+    let diagnostics = both.check();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].path, "windows[0].commands");
+
+    let recording = RecordingBackend::new();
+    assert!(Session::new(both).launch(&recording).is_err());
+    assert!(recording.calls().is_empty());
+}
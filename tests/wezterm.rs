@@ -0,0 +1,124 @@
+use std::io;
+
+use weztermocil::{
+    error::WeztermocilError,
+    wezterm::{command_failure, pane::PaneSize, parse_pane_id, parse_pane_size, spawn_failure},
+};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().copied().map(String::from).collect()
+}
+
+#[test]
+fn tells_why_wezterm_failed() {
+    let missing = spawn_failure(io::Error::from(io::ErrorKind::NotFound));
+    assert!(
+        matches!(missing, WeztermocilError::BinaryMissing { ref binary } if binary == "wezterm")
+    );
+    assert!(matches!(
+        spawn_failure(io::Error::from(io::ErrorKind::PermissionDenied)),
+        WeztermocilError::Io(_)
+    ));
+
+    let unreachable = command_failure(
+        &args(&["cli", "spawn"]),
+        Some(1),
+        String::from("failed to Connect to the mux socket"),
+    );
+    assert!(matches!(
+        unreachable,
+        WeztermocilError::MuxUnreachable { .. }
+    ));
+
+    let failed = command_failure(
+        &args(&["cli", "split-pane", "--pane-id", "7"]),
+        Some(1),
+        String::from("pane 7 not found"),
+    );
+    match failed {
+        WeztermocilError::CommandFailed {
+            command,
+            status,
+            stderr,
+        } => {
+            assert_eq!(command, "wezterm cli split-pane --pane-id 7");
+            assert_eq!(status, Some(1));
+            assert_eq!(stderr, "pane 7 not found");
+        }
+        other => panic!("expected CommandFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn gives_every_error_its_own_exit_code() {
+    let errors = [
+        (
+            WeztermocilError::Io(io::Error::from(io::ErrorKind::Other)),
+            1,
+        ),
+        (WeztermocilError::Config(String::new()), 2),
+        (
+            WeztermocilError::BinaryMissing {
+                binary: String::from("wezterm"),
+            },
+            3,
+        ),
+        (
+            WeztermocilError::MuxUnreachable {
+                reason: String::new(),
+            },
+            4,
+        ),
+        (
+            WeztermocilError::CommandFailed {
+                command: String::new(),
+                status: None,
+                stderr: String::new(),
+            },
+            5,
+        ),
+        (
+            WeztermocilError::BadPaneId {
+                output: String::new(),
+            },
+            6,
+        ),
+        (
+            WeztermocilError::PreFailed {
+                command: String::new(),
+                status: Some(1),
+            },
+            7,
+        ),
+    ];
+
+    for (error, code) in errors {
+        assert_eq!(error.exit_code(), code, "{:?}", error);
+    }
+}
+
+#[test]
+fn parses_what_wezterm_prints() {
+    assert_eq!(parse_pane_id("12\n").unwrap(), "12");
+    for junk in ["", "\n", "pane 12\n", "-1\n", "12 13\n"] {
+        assert!(
+            matches!(parse_pane_id(junk), Err(WeztermocilError::BadPaneId { ref output }) if output == junk),
+            "{:?} should be rejected",
+            junk
+        );
+    }
+
+    let listed = r#"[
+  {"window_id": 0, "tab_id": 0, "pane_id": 3, "size": {"rows": 40, "cols": 120, "pixel_width": 960, "pixel_height": 800, "dpi": 96}},
+  {"window_id": 0, "tab_id": 1, "pane_id": 4, "size": {"rows": 24, "cols": 80, "pixel_width": 640, "pixel_height": 480, "dpi": 96}}
+]"#;
+    assert_eq!(
+        parse_pane_size(listed, "4"),
+        Some(PaneSize {
+            columns: 80,
+            rows: 24
+        })
+    );
+    assert_eq!(parse_pane_size(listed, "5"), None);
+    assert_eq!(parse_pane_size("not json", "3"), None);
+}